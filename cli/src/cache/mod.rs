@@ -0,0 +1,143 @@
+use crate::{config::CacheConfig, utils::FilePort};
+use anyhow::Result;
+use hey_cli_common::GetCliPromptResponse;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Past responses, stored as a single JSON file since it's read on every ask anyway.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PromptCache {
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    key: String,
+    /// Seconds since the unix epoch.
+    created_at: u64,
+    response: GetCliPromptResponse,
+}
+
+impl PromptCache {
+    /// An unreadable cache is treated as empty, it gets overwritten on the next store.
+    pub fn load(files: &dyn FilePort) -> Result<Self> {
+        let Some(path) = cache_path() else {
+            return Ok(Self::default());
+        };
+        let content = files.read_file(&path)?.unwrap_or_default();
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self, files: &dyn FilePort) -> Result<()> {
+        let Some(path) = cache_path() else {
+            return Ok(());
+        };
+        files.overwrite_file(&path, &serde_json::to_string(self)?)
+    }
+
+    pub fn get(&self, key: &str, config: &CacheConfig, now: u64) -> Option<&GetCliPromptResponse> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key && !is_expired(entry, config, now))
+            .map(|entry| &entry.response)
+    }
+
+    /// Drops expired entries, then the oldest ones past `max_entries`.
+    pub fn insert(
+        &mut self,
+        key: String,
+        response: GetCliPromptResponse,
+        config: &CacheConfig,
+        now: u64,
+    ) {
+        self.entries
+            .retain(|entry| entry.key != key && !is_expired(entry, config, now));
+        self.entries.push(CacheEntry {
+            key,
+            created_at: now,
+            response,
+        });
+
+        self.entries.sort_by_key(|entry| entry.created_at);
+        let overflow = self.entries.len().saturating_sub(config.max_entries);
+        self.entries.drain(..overflow);
+    }
+}
+
+fn is_expired(entry: &CacheEntry, config: &CacheConfig, now: u64) -> bool {
+    now.saturating_sub(entry.created_at) >= config.ttl_secs
+}
+
+/// `$XDG_CACHE_HOME/hey-cli/prompts.json`, defaulting to `~/.cache/hey-cli/prompts.json`.
+pub fn cache_path() -> Option<PathBuf> {
+    let cache_dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(cache_home) if !cache_home.is_empty() => PathBuf::from(cache_home),
+        _ => dirs::home_dir()?.join(".cache"),
+    };
+    Some(cache_dir.join("hey-cli/prompts.json"))
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hey_cli_common::CliPrompt;
+
+    fn response(value: &str) -> GetCliPromptResponse {
+        GetCliPromptResponse {
+            prompt: CliPrompt {
+                value: value.to_string(),
+                label: None,
+                explain: None,
+            },
+            alternatives: vec![],
+            template_version: None,
+        }
+    }
+
+    fn config(ttl_secs: u64, max_entries: usize) -> CacheConfig {
+        CacheConfig {
+            enabled: true,
+            ttl_secs,
+            max_entries,
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let config = config(60, 10);
+        let mut cache = PromptCache::default();
+        cache.insert("a".to_string(), response("du -sh ."), &config, 1_000);
+
+        assert_eq!(cache.get("a", &config, 1_059), Some(&response("du -sh .")));
+        assert_eq!(cache.get("a", &config, 1_060), None);
+        assert_eq!(cache.get("b", &config, 1_000), None);
+    }
+
+    #[test]
+    fn insert_replaces_and_evicts_oldest() {
+        let config = config(60, 2);
+        let mut cache = PromptCache::default();
+        cache.insert("a".to_string(), response("1"), &config, 1_000);
+        cache.insert("b".to_string(), response("2"), &config, 1_001);
+        cache.insert("a".to_string(), response("3"), &config, 1_002);
+        assert_eq!(cache.get("a", &config, 1_002), Some(&response("3")));
+        assert_eq!(cache.get("b", &config, 1_002), Some(&response("2")));
+
+        cache.insert("c".to_string(), response("4"), &config, 1_003);
+        assert_eq!(cache.get("b", &config, 1_003), None);
+        assert_eq!(cache.get("a", &config, 1_003), Some(&response("3")));
+        assert_eq!(cache.get("c", &config, 1_003), Some(&response("4")));
+
+        cache.insert("d".to_string(), response("5"), &config, 1_070);
+        assert_eq!(cache.entries.len(), 1);
+    }
+}
@@ -1,29 +1,237 @@
 use crate::{
-    prompt::Prompt,
-    utils::{PortTrait, State},
+    cache::PromptCache,
+    config::{Backend, Config},
+    history::{Origin, context_hash},
+    pick_prompt::PickPrompt,
+    utils::{PortTrait, ShellName, State},
 };
 use anyhow::Result;
-use hey_cli_common::GetCliPromptRequestBody;
+use hey_cli_common::{
+    ApiError, ApiErrorKind, GetCliPromptRequestBody, GetCliPromptResponse, stable_hash,
+};
 use std::collections::HashMap;
 
+/// Context values changing with every edit, left out of the cache key or it would never hit
+/// inside a repository. The answer to "commit this" doesn't depend on how many files changed.
+const VOLATILE_VALUES: &[(&str, &str)] = &[("git", "state"), ("git", "possibly_modified_files")];
+
 pub struct CallServer {
     pub ask: String,
     pub context: HashMap<String, HashMap<String, String>>,
+    /// From the project's `.hey.toml`, see [`crate::config::LocalConfig`].
+    pub instructions: Vec<String>,
+    pub shell: ShellName,
+    pub config: Config,
 }
 
-impl State<Prompt> for CallServer {
-    async fn next(self, port: &impl PortTrait) -> Result<Prompt> {
+impl State<PickPrompt> for CallServer {
+    async fn next(self, port: &impl PortTrait) -> Result<PickPrompt> {
+        let origin = Origin {
+            ask: self.ask.clone(),
+            context_hash: context_hash(&self.context),
+            shell: self.shell,
+        };
         let query = GetCliPromptRequestBody {
             ask: self.ask,
             context: self.context,
+            candidates: self.config.candidates,
+            explain: self.config.explain,
+            instructions: self.instructions,
+            shell: Some(self.shell.to_string()),
         };
 
-        let prompt = port.ask_server_for_prompt(query).await?;
+        let prompt = match self.config.cache.enabled {
+            true => ask_with_cache(port, query, &self.config).await?,
+            false => ask(port, query, &self.config).await?,
+        };
 
         // TODO: check and print update notice
 
-        Ok(Prompt {
-            value: prompt.prompt.value,
+        Ok(PickPrompt {
+            candidates: prompt.candidates(),
+            config: self.config,
+            origin,
         })
     }
 }
+
+async fn ask(
+    port: &impl PortTrait,
+    query: GetCliPromptRequestBody,
+    config: &Config,
+) -> Result<GetCliPromptResponse> {
+    match config.backend {
+        Backend::Server => port.ask_server_for_prompt(query, &config.server).await,
+        Backend::Direct => port.ask_model_for_prompt(query, &config.direct).await,
+    }
+    .map_err(describe_error)
+}
+
+/// A broken cache never fails the ask, it's only a shortcut.
+async fn ask_with_cache(
+    port: &impl PortTrait,
+    query: GetCliPromptRequestBody,
+    config: &Config,
+) -> Result<GetCliPromptResponse> {
+    let key = cache_key(&query, config);
+    let now = crate::cache::now();
+    let mut cache = PromptCache::load(port).unwrap_or_default();
+    if let Some(prompt) = cache.get(&key, &config.cache, now) {
+        tracing::debug!("Cache hit for {key}");
+        return Ok(prompt.clone());
+    }
+
+    let prompt = ask(port, query, config).await?;
+    cache.insert(key, prompt.clone(), &config.cache, now);
+    if let Err(error) = cache.save(port) {
+        tracing::warn!("Could not save the cache: {error}");
+    }
+    Ok(prompt)
+}
+
+/// Also tells backends apart, switching server or model must not answer from the previous one.
+fn cache_key(query: &GetCliPromptRequestBody, config: &Config) -> String {
+    let mut query = query.clone();
+    for (section, key) in VOLATILE_VALUES {
+        if let Some(values) = query.context.get_mut(*section) {
+            values.remove(*key);
+        }
+    }
+    let backend = match config.backend {
+        Backend::Server => format!("server {}", config.server.url),
+        Backend::Direct => format!("direct {} {}", config.direct.base_url, config.direct.model),
+    };
+    stable_hash(&format!("{}\n{backend}", query.cache_key()))
+}
+
+/// Turns errors from the server or the model into a single human-readable line.
+pub fn describe_error(error: anyhow::Error) -> anyhow::Error {
+    let Some(api_error) = error.downcast_ref::<ApiError>() else {
+        return anyhow::anyhow!("Could not get a prompt: {}", error.root_cause());
+    };
+
+    match api_error.kind {
+        ApiErrorKind::InvalidAsk => anyhow::anyhow!("Invalid ask: {}", api_error.message),
+        ApiErrorKind::RateLimited => {
+            anyhow::anyhow!("Rate limited: too many asks, please try again in a moment")
+        }
+        ApiErrorKind::ModelUnavailable => anyhow::anyhow!(
+            "Model unavailable: {}, please try again later",
+            api_error.message
+        ),
+        ApiErrorKind::Internal => anyhow::anyhow!("Server error: {}", api_error.message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_keys_follow_history_and_tell_backends_apart() {
+        let query = |context: &[(&str, &str, &str)]| {
+            let mut query = GetCliPromptRequestBody {
+                ask: "same thing but for staging".to_string(),
+                context: HashMap::new(),
+                candidates: 1,
+                explain: false,
+                instructions: vec![],
+                shell: Some("fish".to_string()),
+            };
+            for (section, key, value) in context {
+                query
+                    .context
+                    .entry(section.to_string())
+                    .or_default()
+                    .insert(key.to_string(), value.to_string());
+            }
+            query
+        };
+        let config = Config::default();
+        let key = cache_key(
+            &query(&[
+                ("history", "commands", "kubectl rollout restart deploy/api"),
+                ("git", "branch", "main"),
+                ("git", "possibly_modified_files", "1"),
+            ]),
+            &config,
+        );
+
+        assert_ne!(
+            key,
+            cache_key(
+                &query(&[
+                    ("history", "commands", "docker compose up"),
+                    ("git", "branch", "main"),
+                    ("git", "possibly_modified_files", "1"),
+                ]),
+                &config
+            )
+        );
+        assert_eq!(
+            key,
+            cache_key(
+                &query(&[
+                    ("history", "commands", "kubectl rollout restart deploy/api"),
+                    ("git", "branch", "main"),
+                    ("git", "possibly_modified_files", "4"),
+                    ("git", "state", "possibly dirty"),
+                ]),
+                &config
+            )
+        );
+
+        let base = query(&[]);
+        let key = cache_key(&base, &config);
+        let mut other_server = Config::default();
+        other_server.set_server_url("https://hey.example.com");
+        assert_ne!(key, cache_key(&base, &other_server));
+
+        let mut direct = Config {
+            backend: Backend::Direct,
+            ..Config::default()
+        };
+        let direct_key = cache_key(&base, &direct);
+        assert_ne!(key, direct_key);
+        direct.direct.model = "llama3.1".to_string();
+        assert_ne!(direct_key, cache_key(&base, &direct));
+    }
+
+    #[test]
+    fn describes_api_errors() {
+        let cases = [
+            (
+                ApiErrorKind::InvalidAsk,
+                "ask is empty",
+                "Invalid ask: ask is empty",
+            ),
+            (
+                ApiErrorKind::RateLimited,
+                "Too many requests to the model",
+                "Rate limited: too many asks, please try again in a moment",
+            ),
+            (
+                ApiErrorKind::ModelUnavailable,
+                "model returned an empty response",
+                "Model unavailable: model returned an empty response, please try again later",
+            ),
+            (ApiErrorKind::Internal, "oops", "Server error: oops"),
+        ];
+        for (kind, message, expected) in cases {
+            let error = anyhow::Error::new(ApiError {
+                kind,
+                message: message.to_string(),
+            });
+            assert_eq!(describe_error(error).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn describes_other_errors_by_their_root_cause() {
+        let error = anyhow::anyhow!("connection refused").context("error sending request");
+        assert_eq!(
+            describe_error(error).to_string(),
+            "Could not get a prompt: connection refused"
+        );
+    }
+}
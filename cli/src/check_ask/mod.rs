@@ -1,15 +1,17 @@
 use crate::{
+    config::Config,
     generate_context::GenerateContext,
-    utils::{PortTrait, State},
+    utils::{PortTrait, ShellName, State},
 };
 use anyhow::Result;
+use hey_cli_common::MAX_ASK_LENGTH;
 
 pub struct CheckAsk {
     pub ask: String,
+    pub shell: ShellName,
+    pub config: Config,
 }
 
-const MAX_ASK_LENGTH: usize = 100;
-
 impl State<GenerateContext> for CheckAsk {
     async fn next(self, _: &impl PortTrait) -> Result<GenerateContext> {
         if self.ask.contains("\n") {
@@ -25,6 +27,6 @@ impl State<GenerateContext> for CheckAsk {
             ));
         }
 
-        Ok(GenerateContext::new(self.ask))
+        Ok(GenerateContext::new(self.ask, self.shell, self.config))
     }
 }
@@ -1,5 +1,6 @@
 use crate::{
     check_ask::CheckAsk,
+    config::Config,
     setup_script::SetupScript,
     utils::{PortTrait, Shell, State},
     what_to_do::WhatToDoAfterCheckSetup,
@@ -9,23 +10,30 @@ use anyhow::Result;
 pub struct CheckSetup {
     pub shell: Option<Shell>,
     pub ask: String,
+    pub config: Config,
 }
 
 impl State<WhatToDoAfterCheckSetup> for CheckSetup {
     async fn next(self, port: &impl PortTrait) -> Result<WhatToDoAfterCheckSetup> {
         if self.shell.is_none() {
             port.log("Setup script not installed");
-            return Ok(WhatToDoAfterCheckSetup::SetupScript(SetupScript));
+            return Ok(WhatToDoAfterCheckSetup::SetupScript(SetupScript {
+                shell: None,
+            }));
         }
 
         let shell = self.shell.unwrap();
         if shell.setup_version != shell.expected_setup_version() {
             port.log("Setup script outdated");
-            return Ok(WhatToDoAfterCheckSetup::SetupScript(SetupScript));
+            return Ok(WhatToDoAfterCheckSetup::SetupScript(SetupScript {
+                shell: Some(shell.name),
+            }));
         }
 
-        Ok(WhatToDoAfterCheckSetup::CheckAsk(CheckAsk {
+        Ok(WhatToDoAfterCheckSetup::CheckAsk(Box::new(CheckAsk {
             ask: self.ask,
-        }))
+            shell: shell.name,
+            config: self.config,
+        })))
     }
 }
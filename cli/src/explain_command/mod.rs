@@ -0,0 +1,65 @@
+use crate::{
+    call_server::describe_error,
+    config::{Backend, Config},
+    generate_context::{ContextSection, generate_context},
+    utils::{PortTrait, ShellName, State},
+};
+use anyhow::Result;
+use hey_cli_common::{ExplainCommandRequestBody, MAX_COMMAND_LENGTH};
+
+/// Longer tokens are not padded, to keep explanations from drifting too far right.
+const MAX_TOKEN_COLUMN_WIDTH: usize = 24;
+
+pub struct ExplainCommand {
+    pub command: String,
+    /// Not known when `hey --what` is run outside of the shell integration.
+    pub shell: Option<ShellName>,
+    pub config: Config,
+}
+
+impl State<()> for ExplainCommand {
+    async fn next(self, port: &impl PortTrait) -> Result<()> {
+        let command = self.command.trim().to_string();
+        if command.is_empty() {
+            return Err(anyhow::anyhow!("Invalid command: command is empty"));
+        }
+        if command.len() > MAX_COMMAND_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Invalid command: max length of {} characters reached",
+                MAX_COMMAND_LENGTH
+            ));
+        }
+
+        let query = ExplainCommandRequestBody {
+            command,
+            context: generate_context(&self.config.context, self.shell, &ContextSection::new())?,
+        };
+
+        let explanation = match self.config.backend {
+            Backend::Server => {
+                port.ask_server_for_explanation(query, &self.config.server)
+                    .await
+            }
+            Backend::Direct => {
+                port.ask_model_for_explanation(query, &self.config.direct)
+                    .await
+            }
+        }
+        .map_err(describe_error)?;
+
+        port.log(explanation.summary);
+
+        let width = explanation
+            .parts
+            .iter()
+            .map(|part| part.token.chars().count())
+            .filter(|width| *width <= MAX_TOKEN_COLUMN_WIDTH)
+            .max()
+            .unwrap_or_default();
+        for part in explanation.parts {
+            port.log(format!("  {:width$}  {}", part.token, part.explain));
+        }
+
+        Ok(())
+    }
+}
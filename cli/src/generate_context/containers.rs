@@ -0,0 +1,382 @@
+use super::{ContextProvider, ContextSection};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Kubernetes and docker targets, and whether we run inside a container ourselves. Opt-in,
+/// context names can be as telling as an EKS ARN with the AWS account id in it.
+pub struct Containers;
+
+impl ContextProvider for Containers {
+    fn name(&self) -> &str {
+        "containers"
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        let home = dirs::home_dir();
+        Ok(describe(
+            |key| std::env::var(key).ok().filter(|value| !value.is_empty()),
+            home.as_deref(),
+            Path::new("/"),
+        ))
+    }
+}
+
+/// `root` is `/`, but for tests.
+fn describe(
+    env: impl Fn(&str) -> Option<String>,
+    home: Option<&Path>,
+    root: &Path,
+) -> ContextSection {
+    let mut section = ContextSection::new();
+
+    let kubeconfigs: Vec<_> = match env("KUBECONFIG") {
+        Some(paths) => std::env::split_paths(&paths).collect(),
+        None => home
+            .map(|home| home.join(".kube/config"))
+            .into_iter()
+            .collect(),
+    };
+    if let Some((context, namespace)) = kube_context(&kubeconfigs) {
+        section.insert("kube_context".to_string(), context);
+        section.insert("kube_namespace".to_string(), namespace);
+    }
+
+    let runtime_dir = env("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let docker_sockets = [
+        runtime_dir.as_ref().map(|dir| dir.join("docker.sock")),
+        Some(root.join("var/run/docker.sock")),
+        home.map(|home| home.join(".docker/run/docker.sock")),
+        home.map(|home| home.join(".colima/default/docker.sock")),
+        home.map(|home| home.join(".orbstack/run/docker.sock")),
+    ];
+    let podman_sockets = [
+        runtime_dir
+            .as_ref()
+            .map(|dir| dir.join("podman/podman.sock")),
+        Some(root.join("run/podman/podman.sock")),
+    ];
+    let docker_socket = match env("DOCKER_HOST") {
+        Some(host) => Some(host),
+        None => existing(docker_sockets),
+    };
+    if let Some(socket) = &docker_socket {
+        section.insert("docker_socket".to_string(), socket.clone());
+    }
+    if let Some(socket) = existing(podman_sockets) {
+        section.insert("podman_socket".to_string(), socket);
+    }
+
+    // like `docker context show`
+    let docker_config = match env("DOCKER_CONFIG") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => home.map(|home| home.join(".docker")),
+    };
+    let current_context = docker_config
+        .map(|dir| dir.join("config.json"))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|config| Some(config.get("currentContext")?.as_str()?.to_string()));
+    let docker_context = env("DOCKER_CONTEXT")
+        .or(current_context)
+        .or(docker_socket.map(|_| "default".to_string()));
+    if let Some(context) = docker_context {
+        section.insert("docker_context".to_string(), context);
+    }
+
+    if let Some(container) = inside_container(&env, root) {
+        section.insert("inside_container".to_string(), container.to_string());
+    }
+
+    section
+}
+
+fn existing(paths: impl IntoIterator<Item = Option<PathBuf>>) -> Option<String> {
+    paths
+        .into_iter()
+        .flatten()
+        .find(|path| path.exists())
+        .map(|path| path.display().to_string())
+}
+
+fn inside_container(env: &impl Fn(&str) -> Option<String>, root: &Path) -> Option<&'static str> {
+    if env("KUBERNETES_SERVICE_HOST").is_some() {
+        return Some("kubernetes");
+    }
+    if root.join(".dockerenv").exists() {
+        return Some("docker");
+    }
+    if root.join("run/.containerenv").exists() {
+        return Some("podman");
+    }
+    // set by podman, systemd-nspawn, lxc, ...
+    if env("container").is_some() {
+        return Some("container");
+    }
+
+    let cgroup = std::fs::read_to_string(root.join("proc/1/cgroup")).unwrap_or_default();
+    [
+        ("kubepods", "kubernetes"),
+        ("docker", "docker"),
+        ("libpod", "podman"),
+        ("containerd", "containerd"),
+        ("lxc", "lxc"),
+    ]
+    .into_iter()
+    .find(|(marker, _)| cgroup.contains(marker))
+    .map(|(_, container)| container)
+}
+
+/// The current context and its namespace, `kubectl` merging the `KUBECONFIG` files
+/// by keeping the first `current-context` and the first definition of each context.
+fn kube_context(kubeconfigs: &[PathBuf]) -> Option<(String, String)> {
+    let kubeconfigs = kubeconfigs
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|content| parse_kubeconfig(&content))
+        .collect::<Vec<_>>();
+
+    let current = kubeconfigs
+        .iter()
+        .find_map(|kubeconfig| kubeconfig.current_context.clone())?;
+    let namespace = kubeconfigs
+        .iter()
+        .flat_map(|kubeconfig| &kubeconfig.contexts)
+        .find(|(name, _)| *name == current)
+        .and_then(|(_, namespace)| namespace.clone())
+        .unwrap_or_else(|| "default".to_string());
+    Some((current, namespace))
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct Kubeconfig {
+    current_context: Option<String>,
+    /// Names and namespaces.
+    contexts: Vec<(String, Option<String>)>,
+}
+
+/// Reads the block style YAML `kubectl` writes, or JSON.
+fn parse_kubeconfig(content: &str) -> Kubeconfig {
+    if content.trim_start().starts_with('{') {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(content) else {
+            return Kubeconfig::default();
+        };
+        let string = |value: &serde_json::Value| value.as_str().map(str::to_string);
+        let contexts = json
+            .get("contexts")
+            .and_then(|contexts| contexts.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|context| {
+                let name = string(context.get("name")?)?;
+                let namespace = context
+                    .get("context")
+                    .and_then(|context| context.get("namespace"))
+                    .and_then(string);
+                Some((name, namespace))
+            })
+            .collect();
+        return Kubeconfig {
+            current_context: json.get("current-context").and_then(string),
+            contexts,
+        };
+    }
+
+    let mut kubeconfig = Kubeconfig::default();
+    let mut in_contexts = false;
+    // where the `- ` of each context is, nested lists are deeper
+    let mut list_indent = None;
+    let mut item: Option<Item> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+
+        if indent == 0 && !trimmed.starts_with("- ") {
+            finish(&mut item, &mut kubeconfig);
+            in_contexts = trimmed.starts_with("contexts:");
+            list_indent = None;
+            if let Some(value) = trimmed.strip_prefix("current-context:") {
+                kubeconfig.current_context = Some(unquote(value)).filter(|value| !value.is_empty());
+            }
+            continue;
+        }
+        if !in_contexts {
+            continue;
+        }
+
+        let (indent, key) = match trimmed.strip_prefix("- ") {
+            Some(rest) => {
+                let key_indent = indent + 2 + (rest.len() - rest.trim_start().len());
+                if *list_indent.get_or_insert(indent) == indent {
+                    finish(&mut item, &mut kubeconfig);
+                    item = Some(Item {
+                        indent: key_indent,
+                        ..Item::default()
+                    });
+                }
+                (key_indent, rest.trim_start())
+            }
+            None => (indent, trimmed),
+        };
+        let Some(item) = &mut item else {
+            continue;
+        };
+        if let Some(value) = key.strip_prefix("name:")
+            && indent == item.indent
+        {
+            item.name = Some(unquote(value));
+        }
+        if let Some(value) = key.strip_prefix("namespace:")
+            && indent > item.indent
+        {
+            item.namespace = Some(unquote(value));
+        }
+    }
+    finish(&mut item, &mut kubeconfig);
+
+    kubeconfig
+}
+
+/// A context being read, `indent` being the one of its own keys.
+#[derive(Default)]
+struct Item {
+    indent: usize,
+    name: Option<String>,
+    namespace: Option<String>,
+}
+
+fn finish(item: &mut Option<Item>, kubeconfig: &mut Kubeconfig) {
+    if let Some(Item {
+        name: Some(name),
+        namespace,
+        ..
+    }) = item.take()
+    {
+        kubeconfig.contexts.push((name, namespace));
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(unquoted) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return unquoted.to_string();
+        }
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_context::TempDir;
+
+    const KUBECONFIG: &str = r#"apiVersion: v1
+clusters:
+- cluster:
+    server: https://staging.example.com
+  name: staging
+contexts:
+- context:
+    cluster: prod
+    user: admin
+    extensions:
+    - extension:
+        name: not-a-context
+      name: context_info
+  name: prod
+- name: "staging"
+  context:
+    cluster: staging
+    namespace: web
+current-context: staging
+kind: Config
+users: []
+"#;
+
+    #[test]
+    fn parses_kubeconfig() {
+        assert_eq!(
+            parse_kubeconfig(KUBECONFIG),
+            Kubeconfig {
+                current_context: Some("staging".to_string()),
+                contexts: vec![
+                    ("prod".to_string(), None),
+                    ("staging".to_string(), Some("web".to_string())),
+                ],
+            }
+        );
+        assert_eq!(
+            parse_kubeconfig(
+                r#"{"current-context": "prod", "contexts": [{"name": "prod", "context": {"namespace": "api"}}]}"#
+            ),
+            Kubeconfig {
+                current_context: Some("prod".to_string()),
+                contexts: vec![("prod".to_string(), Some("api".to_string()))],
+            }
+        );
+    }
+
+    #[test]
+    fn merges_kubeconfigs() {
+        let dir = TempDir::new("containers-kube");
+        dir.write(
+            "a",
+            "contexts:\n- name: dev\n  context:\n    namespace: team\n",
+        );
+        dir.write(
+            "b",
+            KUBECONFIG.replace("current-context: staging", "current-context: dev"),
+        );
+
+        let paths = [dir.0.join("missing"), dir.0.join("a"), dir.0.join("b")];
+        assert_eq!(
+            kube_context(&paths),
+            Some(("dev".to_string(), "team".to_string()))
+        );
+        assert_eq!(kube_context(&paths[..2]), None);
+    }
+
+    #[test]
+    fn describes_docker_and_container() {
+        let root = TempDir::new("containers-root");
+        let home = TempDir::new("containers-home");
+        root.write("var/run/docker.sock", "");
+        root.write("proc/1/cgroup", "0::/kubepods/besteffort/pod1\n");
+        home.write(".kube/config", KUBECONFIG);
+        home.write(".docker/config.json", r#"{"currentContext": "colima"}"#);
+
+        let section = describe(|_| None, Some(&home.0), &root.0);
+        assert_eq!(section["kube_context"], "staging");
+        assert_eq!(section["kube_namespace"], "web");
+        assert_eq!(
+            section["docker_socket"],
+            root.0.join("var/run/docker.sock").display().to_string()
+        );
+        assert_eq!(section["docker_context"], "colima");
+        assert_eq!(section["inside_container"], "kubernetes");
+        assert!(!section.contains_key("podman_socket"));
+
+        root.write(".dockerenv", "");
+        let env = |key: &str| match key {
+            "DOCKER_HOST" => Some("tcp://10.0.0.2:2376".to_string()),
+            "DOCKER_CONTEXT" => Some("remote".to_string()),
+            _ => None,
+        };
+        let section = describe(env, None, &root.0);
+        assert_eq!(section["docker_socket"], "tcp://10.0.0.2:2376");
+        assert_eq!(section["docker_context"], "remote");
+        assert_eq!(section["inside_container"], "docker");
+        assert!(!section.contains_key("kube_context"));
+    }
+}
@@ -0,0 +1,203 @@
+use super::{ContextProvider, ContextSection};
+use crate::config::{CwdConfig, CwdPath};
+use anyhow::Result;
+use hey_cli_common::stable_hash;
+use std::{collections::HashMap, path::Path};
+
+/// Listed entries are cut once their names add up to this many characters.
+const MAX_ENTRIES_LENGTH: usize = 1_500;
+/// Only the most common extensions are told.
+const MAX_EXTENSIONS: usize = 10;
+
+/// The current directory and what's in it, opt-in since it names the user's files.
+pub struct Cwd {
+    pub config: CwdConfig,
+}
+
+impl ContextProvider for Cwd {
+    fn name(&self) -> &str {
+        "cwd"
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        let cwd = std::env::current_dir()?;
+        describe(&cwd, dirs::home_dir().as_deref(), &self.config)
+    }
+}
+
+fn describe(cwd: &Path, home: Option<&Path>, config: &CwdConfig) -> Result<ContextSection> {
+    let mut section = ContextSection::new();
+
+    let path = match (
+        config.path,
+        home.and_then(|home| cwd.strip_prefix(home).ok()),
+    ) {
+        (CwdPath::Home, Some(relative)) if relative.as_os_str().is_empty() => "~".to_string(),
+        (CwdPath::Home, Some(relative)) => format!("~/{}", relative.display()),
+        (CwdPath::Home | CwdPath::Full, _) => cwd.display().to_string(),
+        (CwdPath::Hashed, _) => stable_hash(&cwd.to_string_lossy()),
+    };
+    section.insert("path".to_string(), path);
+
+    // `ls -F` style, so the model can tell directories, links and scripts apart
+    let mut entries = vec![];
+    let mut extensions = HashMap::<String, usize>::new();
+    for entry in std::fs::read_dir(cwd)?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(metadata) = entry.path().symlink_metadata() else {
+            continue;
+        };
+        let (kind, suffix) = if metadata.is_symlink() {
+            (0, "@")
+        } else if metadata.is_dir() {
+            (0, "/")
+        } else if is_executable(&metadata) {
+            (1, "*")
+        } else {
+            (1, "")
+        };
+        if metadata.is_file()
+            && let Some((stem, extension)) = name.rsplit_once('.')
+            && !stem.is_empty()
+        {
+            *extensions.entry(extension.to_lowercase()).or_default() += 1;
+        }
+        entries.push((kind, format!("{name}{suffix}")));
+    }
+    entries.sort_by_key(|(kind, name)| (*kind, name.to_lowercase()));
+
+    section.insert("entry_count".to_string(), entries.len().to_string());
+    if !entries.is_empty() {
+        let names = entries.iter().map(|(_, name)| name.as_str());
+        section.insert(
+            "entries".to_string(),
+            list(names, entries.len(), config.max_entries),
+        );
+    }
+
+    let mut extensions = extensions.into_iter().collect::<Vec<_>>();
+    extensions.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    if !extensions.is_empty() {
+        let extensions = extensions
+            .iter()
+            .take(MAX_EXTENSIONS)
+            .map(|(extension, count)| format!("{extension} ({count})"))
+            .collect::<Vec<_>>();
+        section.insert("extensions".to_string(), extensions.join(", "));
+    }
+
+    Ok(section)
+}
+
+fn list<'a>(names: impl Iterator<Item = &'a str>, count: usize, max_entries: usize) -> String {
+    let mut listed = vec![];
+    let mut length = 0;
+    for name in names.take(max_entries) {
+        if length + name.len() > MAX_ENTRIES_LENGTH {
+            break;
+        }
+        length += name.len() + 2;
+        listed.push(name);
+    }
+
+    let mut list = listed.join(", ");
+    if count > listed.len() {
+        list.push_str(&format!(", and {} more", count - listed.len()));
+    }
+    list
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_context::TempDir;
+
+    #[test]
+    fn lists_entries_and_extensions() {
+        let dir = TempDir::new("cwd");
+        for path in [
+            "IMG_1.JPG",
+            "img_2.jpg",
+            "b.png",
+            ".env",
+            "notes",
+            "src/main.rs",
+        ] {
+            dir.write(path, "");
+        }
+
+        let section = describe(&dir.0, None, &CwdConfig::default()).unwrap();
+        assert_eq!(section["path"], dir.0.display().to_string());
+        assert_eq!(section["entry_count"], "6");
+        assert_eq!(
+            section["entries"],
+            "src/, .env, b.png, IMG_1.JPG, img_2.jpg, notes"
+        );
+        assert_eq!(section["extensions"], "jpg (2), png (1)");
+
+        let config = CwdConfig {
+            max_entries: 2,
+            ..CwdConfig::default()
+        };
+        let section = describe(&dir.0, None, &config).unwrap();
+        assert_eq!(section["entries"], "src/, .env, and 4 more");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn marks_links_and_executables() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("cwd-types");
+        dir.write("run.sh", "#!/bin/sh\n");
+        dir.write("src/main.rs", "");
+        let permissions = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(dir.0.join("run.sh"), permissions).unwrap();
+        std::os::unix::fs::symlink(dir.0.join("src"), dir.0.join("latest")).unwrap();
+
+        let section = describe(&dir.0, None, &CwdConfig::default()).unwrap();
+        assert_eq!(section["entries"], "latest@, src/, run.sh*");
+    }
+
+    #[test]
+    fn path_modes() {
+        let dir = TempDir::new("cwd-path");
+        dir.write("photos/a.jpg", "");
+        let cwd = dir.0.join("photos");
+
+        let path = |path, home: &Path| {
+            let config = CwdConfig {
+                path,
+                ..CwdConfig::default()
+            };
+            describe(&cwd, Some(home), &config).unwrap()["path"].clone()
+        };
+        assert_eq!(path(CwdPath::Home, &dir.0), "~/photos");
+        assert_eq!(path(CwdPath::Home, &cwd), "~");
+        assert_eq!(
+            path(CwdPath::Home, Path::new("/elsewhere")),
+            cwd.display().to_string()
+        );
+        assert_eq!(path(CwdPath::Full, &dir.0), cwd.display().to_string());
+        assert_eq!(
+            path(CwdPath::Hashed, &dir.0),
+            stable_hash(&cwd.to_string_lossy())
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use super::{ContextProvider, ContextSection};
+use crate::config::ContextPlugin;
+use anyhow::{Context, Result};
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// Bigger outputs drop the section, they would bloat every ask and its cache key.
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+
+/// A command from `[[context.plugins]]` printing a JSON object, eg: `{"profile": "staging"}`.
+pub struct External {
+    name: String,
+    command: Vec<String>,
+    timeout: Duration,
+}
+
+impl External {
+    pub fn new(plugin: &ContextPlugin, timeout: Duration) -> Self {
+        Self {
+            name: plugin.name.clone(),
+            command: plugin.command.clone(),
+            timeout,
+        }
+    }
+}
+
+impl ContextProvider for External {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Killed once past the timeout, so it doesn't outlive hey-cli.
+    fn provide(&self) -> Result<ContextSection> {
+        let (program, args) = self.command.split_first().context("Empty plugin command")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Could not run {program}"))?;
+
+        // read while waiting, a plugin filling the pipe would block on its writes otherwise
+        let mut stdout = child.stdout.take().context("Plugin stdout is not piped")?;
+        let reader = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+            let mut output = vec![];
+            (&mut stdout)
+                .take(MAX_OUTPUT_BYTES + 1)
+                .read_to_end(&mut output)?;
+            // the rest is drained, or the plugin would block until the timeout
+            std::io::copy(&mut stdout, &mut std::io::sink())?;
+            Ok(output)
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!("{program} timed out");
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        if !status.success() {
+            anyhow::bail!("{program} exited with {status}");
+        }
+
+        let output = reader
+            .join()
+            .map_err(|_| anyhow::anyhow!("Could not read the output of {program}"))??;
+        if output.len() as u64 > MAX_OUTPUT_BYTES {
+            anyhow::bail!(
+                "{program} printed more than {} KiB",
+                MAX_OUTPUT_BYTES / 1024
+            );
+        }
+        parse_output(&String::from_utf8(output).context("Plugin output is not UTF-8")?)
+    }
+}
+
+/// Non-string values are kept as their JSON text.
+fn parse_output(output: &str) -> Result<ContextSection> {
+    let object = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(output)
+        .context("Plugin output is not a JSON object")?;
+
+    Ok(object
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn caps_the_output() {
+        let plugin = |bytes: u64| ContextPlugin {
+            name: "big".to_string(),
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                format!(
+                    r#"printf '{{"value": "'; head -c {bytes} /dev/zero | tr '\0' a; printf '"}}'"#
+                ),
+            ],
+        };
+        let section = External::new(&plugin(60_000), Duration::from_secs(5))
+            .provide()
+            .unwrap();
+        assert_eq!(section["value"].len(), 60_000);
+
+        // far bigger than the pipe buffer, failing on the cap rather than the timeout
+        let error = External::new(&plugin(1_000_000), Duration::from_secs(5))
+            .provide()
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "sh printed more than 64 KiB");
+    }
+
+    #[test]
+    fn parses_json_objects_only() {
+        let section =
+            parse_output(r#"{"profile": "staging", "regions": ["eu", "us"], "count": 2}"#).unwrap();
+        assert_eq!(section["profile"], "staging");
+        assert_eq!(section["regions"], r#"["eu","us"]"#);
+        assert_eq!(section["count"], "2");
+
+        assert!(parse_output("profile=staging").is_err());
+        assert!(parse_output(r#"["staging"]"#).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use super::{ContextProvider, ContextSection};
+use anyhow::Result;
+use std::path::Path;
+
+/// Where package managers are installed, `nix` being told apart by its store instead.
+const PACKAGE_MANAGERS: &[(&str, &[&str])] = &[
+    ("apt", &["usr/bin/apt"]),
+    ("dnf", &["usr/bin/dnf"]),
+    ("yum", &["usr/bin/yum"]),
+    ("pacman", &["usr/bin/pacman"]),
+    ("apk", &["sbin/apk", "usr/bin/apk"]),
+    ("zypper", &["usr/bin/zypper"]),
+    ("nix", &["nix/store"]),
+    (
+        "brew",
+        &[
+            "opt/homebrew/bin/brew",
+            "usr/local/bin/brew",
+            "home/linuxbrew/.linuxbrew/bin/brew",
+        ],
+    ),
+];
+
+/// The operating system, and how software gets installed and run on it.
+pub struct General;
+
+impl ContextProvider for General {
+    fn name(&self) -> &str {
+        "general"
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        let mut section = ContextSection::new();
+        let info = os_info::get();
+
+        section.insert("os_type".to_string(), info.os_type().to_string());
+        section.insert("os_version".to_string(), info.version().to_string());
+        section.insert("os_bitness".to_string(), info.bitness().to_string());
+        if let Some(arch) = info.architecture() {
+            section.insert("os_architecture".to_string(), arch.to_string());
+        }
+
+        let id = std::process::Command::new("id")
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+        section.extend(describe(Path::new("/"), id.as_deref()));
+
+        Ok(section)
+    }
+}
+
+/// `root` is `/`, but for tests, and `id` the output of the `id` command.
+fn describe(root: &Path, id: Option<&str>) -> ContextSection {
+    let mut section = ContextSection::new();
+
+    let os_release = ["etc/os-release", "usr/lib/os-release"]
+        .into_iter()
+        .find_map(|path| std::fs::read_to_string(root.join(path)).ok())
+        .unwrap_or_default();
+    for (key, field) in [("distro", "PRETTY_NAME"), ("distro_like", "ID_LIKE")] {
+        if let Some(value) = os_release_field(&os_release, field) {
+            section.insert(key.to_string(), value);
+        }
+    }
+
+    let package_managers = PACKAGE_MANAGERS
+        .iter()
+        .filter(|(_, paths)| paths.iter().any(|path| root.join(path).exists()))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    if !package_managers.is_empty() {
+        section.insert("package_managers".to_string(), package_managers.join(", "));
+    }
+
+    if let Some(init_system) = init_system(root) {
+        section.insert("init_system".to_string(), init_system.to_string());
+    }
+    if let Some(libc) = libc(root) {
+        section.insert("libc".to_string(), libc.to_string());
+    }
+    // booleans, as `root` or `user` could be the username and get redacted
+    if let Some((is_root, can_sudo)) = id.and_then(|id| privileges(root, id)) {
+        section.insert("is_root".to_string(), is_root.to_string());
+        section.insert("can_sudo".to_string(), can_sudo.to_string());
+    }
+
+    section
+}
+
+fn os_release_field(os_release: &str, field: &str) -> Option<String> {
+    let value = os_release
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix('='))?
+        .trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value);
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn init_system(root: &Path) -> Option<&'static str> {
+    let markers = [
+        ("run/systemd/system", "systemd"),
+        ("run/openrc", "openrc"),
+        ("sbin/openrc", "openrc"),
+        ("run/runit", "runit"),
+        ("sbin/launchd", "launchd"),
+    ];
+    if let Some((_, init_system)) = markers
+        .into_iter()
+        .find(|(marker, _)| root.join(marker).exists())
+    {
+        return Some(init_system);
+    }
+
+    // in containers pid 1 is usually the app itself, which tells nothing
+    let comm = std::fs::read_to_string(root.join("proc/1/comm")).unwrap_or_default();
+    ["systemd", "openrc-init", "runit", "s6-svscan", "launchd"]
+        .into_iter()
+        .find(|init_system| comm.trim() == *init_system)
+}
+
+/// Told by the dynamic loader, glibc's is `ld-linux*` and musl's `ld-musl-*`.
+fn libc(root: &Path) -> Option<&'static str> {
+    ["lib", "lib64", "usr/lib"]
+        .into_iter()
+        .filter_map(|dir| std::fs::read_dir(root.join(dir)).ok())
+        .flat_map(|entries| entries.flatten())
+        .find_map(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with("ld-musl-") {
+                Some("musl")
+            } else if name.starts_with("ld-linux") {
+                Some("glibc")
+            } else {
+                None
+            }
+        })
+}
+
+/// Whether the user is root, and whether they're in a group allowed to use `sudo` or `doas`.
+fn privileges(root: &Path, id: &str) -> Option<(bool, bool)> {
+    // uid=1000(alice) gid=1000(alice) groups=1000(alice),27(sudo)
+    let uid = id.split_whitespace().find_map(|field| {
+        let uid = field.strip_prefix("uid=")?;
+        uid.split('(').next()?.parse::<u32>().ok()
+    })?;
+    if uid == 0 {
+        return Some((true, false));
+    }
+
+    let groups = id
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("groups="))
+        .unwrap_or_default();
+    let in_admin_group = groups.split(',').any(|group| {
+        let name = group
+            .split_once('(')
+            .map_or("", |(_, name)| name.trim_end_matches(')'));
+        ["sudo", "wheel", "admin"].contains(&name)
+    });
+    let has_sudo = [
+        "usr/bin/sudo",
+        "bin/sudo",
+        "usr/bin/doas",
+        "usr/local/bin/doas",
+    ]
+    .into_iter()
+    .any(|path| root.join(path).exists());
+
+    Some((false, in_admin_group && has_sudo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::RedactConfig, generate_context::TempDir, redact::Redactor};
+
+    #[test]
+    fn debian_with_systemd() {
+        let root = TempDir::new("general-debian");
+        root.write(
+            "etc/os-release",
+            "PRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\nID=ubuntu\nID_LIKE=debian\n",
+        );
+        for path in [
+            "usr/bin/apt",
+            "usr/bin/sudo",
+            "run/systemd/system/.keep",
+            "lib64/ld-linux-x86-64.so.2",
+            "home/linuxbrew/.linuxbrew/bin/brew",
+        ] {
+            root.write(path, "");
+        }
+
+        let section = describe(
+            &root.0,
+            Some("uid=1000(alice) gid=1000(alice) groups=1000(alice),4(adm),27(sudo)\n"),
+        );
+        assert_eq!(section["distro"], "Ubuntu 24.04.1 LTS");
+        assert_eq!(section["distro_like"], "debian");
+        assert_eq!(section["package_managers"], "apt, brew");
+        assert_eq!(section["init_system"], "systemd");
+        assert_eq!(section["libc"], "glibc");
+        assert_eq!(section["is_root"], "false");
+        assert_eq!(section["can_sudo"], "true");
+    }
+
+    #[test]
+    fn alpine_container() {
+        let root = TempDir::new("general-alpine");
+        root.write(
+            "usr/lib/os-release",
+            "PRETTY_NAME='Alpine Linux v3.20'\nID=alpine\n",
+        );
+        root.write("sbin/apk", "");
+        root.write("lib/ld-musl-x86_64.so.1", "");
+        root.write("proc/1/comm", "sleep\n");
+
+        let section = describe(&root.0, Some("uid=0(root) gid=0(root) groups=0(root)\n"));
+        assert_eq!(section["distro"], "Alpine Linux v3.20");
+        assert!(!section.contains_key("distro_like"));
+        assert_eq!(section["package_managers"], "apk");
+        assert!(!section.contains_key("init_system"));
+        assert_eq!(section["libc"], "musl");
+        assert_eq!(section["is_root"], "true");
+
+        let id = "uid=1000(bob) gid=1000(bob) groups=1000(bob),10(wheel)";
+        assert_eq!(privileges(&root.0, id), Some((false, false)));
+        assert_eq!(describe(&root.0, None).get("is_root"), None);
+    }
+
+    #[test]
+    fn survives_redaction_on_default_names() {
+        let root = TempDir::new("general-redacted");
+        let os_release = "PRETTY_NAME=\"Zorin OS 17.1\"\nID=zorin\nID_LIKE=\"ubuntu debian\"\n";
+        root.write("etc/os-release", os_release);
+        root.write("usr/bin/apt", "");
+
+        let mut section = describe(
+            &root.0,
+            Some("uid=1000(ubuntu) gid=1000(ubuntu) groups=1000(ubuntu),27(sudo)"),
+        );
+        section.insert("os_type".to_string(), "Ubuntu".to_string());
+        // cloud images default to the distro as username, installs as hostname
+        let redactor = Redactor::with_identity(
+            &RedactConfig::default(),
+            Some("ubuntu".to_string()),
+            Some("zorin".to_string()),
+            os_release,
+        )
+        .unwrap();
+        let redacted = section
+            .iter()
+            .map(|(key, value)| (key.clone(), redactor.redact(value)))
+            .collect::<ContextSection>();
+        assert_eq!(redacted, section);
+        assert_eq!(redacted["distro"], "Zorin OS 17.1");
+        assert_eq!(redacted["distro_like"], "ubuntu debian");
+    }
+}
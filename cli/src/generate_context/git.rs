@@ -0,0 +1,484 @@
+use super::{ContextProvider, ContextSection};
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Bigger indexes are not compared against the work tree, a `stat` per file would take the
+/// whole time budget on a cold cache or a network filesystem, dropping the branch with it.
+const MAX_INDEX_ENTRIES: usize = 3_000;
+
+/// The git repository containing the current directory, read from `.git` without running git.
+pub struct Git;
+
+impl ContextProvider for Git {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        describe(&std::env::current_dir()?)
+    }
+}
+
+fn describe(cwd: &Path) -> Result<ContextSection> {
+    let mut section = ContextSection::new();
+    let Some(repository) = Repository::find(cwd)? else {
+        return Ok(section);
+    };
+    let config = repository.config()?;
+
+    let head = std::fs::read_to_string(repository.git_dir.join("HEAD"))?;
+    match head.trim().strip_prefix("ref: refs/heads/") {
+        Some(branch) => {
+            section.insert("branch".to_string(), branch.to_string());
+            if let Some(upstream) = upstream(&config, branch) {
+                section.insert("upstream".to_string(), upstream);
+            }
+        }
+        None => {
+            let commit = head.trim().get(..7).unwrap_or(head.trim());
+            section.insert("branch".to_string(), format!("detached at {commit}"));
+        }
+    }
+
+    let remotes = config
+        .keys()
+        .filter_map(|(section, subsection)| (section == "remote").then_some(subsection.as_str()))
+        .collect::<Vec<_>>();
+    if !remotes.is_empty() {
+        section.insert("remotes".to_string(), remotes.join(", "));
+    }
+
+    if let Some(operation) = repository.operation_in_progress() {
+        section.insert("in_progress".to_string(), operation.to_string());
+    }
+
+    // a file touched but left unchanged looks modified, without hashing its content
+    if let Some(modified) = repository.modified_files()? {
+        let state = if modified == 0 {
+            "clean"
+        } else {
+            "possibly dirty"
+        };
+        section.insert("state".to_string(), state.to_string());
+        section.insert("possibly_modified_files".to_string(), modified.to_string());
+    }
+
+    Ok(section)
+}
+
+/// Config values by `(section, subsection)` then key, eg: `("branch", "main")` -> `remote`.
+type GitConfig = HashMap<(String, String), HashMap<String, String>>;
+
+fn upstream(config: &GitConfig, branch: &str) -> Option<String> {
+    let values = config.get(&("branch".to_string(), branch.to_string()))?;
+    let remote = values.get("remote")?;
+    let merge = values.get("merge")?;
+    let merge = merge.strip_prefix("refs/heads/").unwrap_or(merge);
+    match remote.as_str() {
+        "." => Some(merge.to_string()),
+        remote => Some(format!("{remote}/{merge}")),
+    }
+}
+
+struct Repository {
+    work_tree: PathBuf,
+    /// Per worktree: `HEAD`, `index`, and in-progress operations.
+    git_dir: PathBuf,
+    /// Shared by all worktrees: `config`.
+    common_dir: PathBuf,
+}
+
+impl Repository {
+    fn find(cwd: &Path) -> Result<Option<Self>> {
+        for dir in cwd.ancestors() {
+            let dot_git = dir.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            } else if dot_git.is_file() {
+                // worktrees and submodules: `gitdir: <path>`
+                let content = std::fs::read_to_string(&dot_git)?;
+                let path = content
+                    .trim()
+                    .strip_prefix("gitdir: ")
+                    .context("Invalid .git file")?;
+                dir.join(path)
+            } else {
+                continue;
+            };
+
+            let common_dir = match std::fs::read_to_string(git_dir.join("commondir")) {
+                Ok(common_dir) => git_dir.join(common_dir.trim()),
+                Err(_) => git_dir.clone(),
+            };
+            return Ok(Some(Self {
+                work_tree: dir.to_path_buf(),
+                git_dir,
+                common_dir,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn config(&self) -> Result<GitConfig> {
+        let content = std::fs::read_to_string(self.common_dir.join("config")).unwrap_or_default();
+        Ok(parse_config(&content))
+    }
+
+    fn operation_in_progress(&self) -> Option<&'static str> {
+        [
+            ("rebase-merge", "rebase"),
+            ("rebase-apply", "rebase"),
+            ("MERGE_HEAD", "merge"),
+            ("CHERRY_PICK_HEAD", "cherry-pick"),
+            ("REVERT_HEAD", "revert"),
+            ("BISECT_LOG", "bisect"),
+        ]
+        .into_iter()
+        .find(|(marker, _)| self.git_dir.join(marker).exists())
+        .map(|(_, operation)| operation)
+    }
+
+    /// Tracked files whose size or modification time differ from the index, or were deleted,
+    /// `None` when the index is missing or too big. Staged changes are not detected.
+    fn modified_files(&self) -> Result<Option<usize>> {
+        let Ok(index) = std::fs::read(self.git_dir.join("index")) else {
+            return Ok(None);
+        };
+        let Some(entries) = parse_index(&index)? else {
+            return Ok(None);
+        };
+
+        let modified = entries
+            .iter()
+            .filter(|entry| is_modified(entry, &self.work_tree.join(&entry.path)))
+            .count();
+        Ok(Some(modified))
+    }
+}
+
+fn parse_config(content: &str) -> GitConfig {
+    let mut config = GitConfig::new();
+    let mut current = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(header) = line
+            .strip_prefix('[')
+            .and_then(|line| line.split(']').next())
+        {
+            // `[branch "main"]`, or the deprecated `[branch.main]`
+            let (section, subsection) = match header.split_once(char::is_whitespace) {
+                Some((section, subsection)) => (section, subsection.trim().trim_matches('"')),
+                None => header.split_once('.').unwrap_or((header, "")),
+            };
+            current = Some((section.to_lowercase(), subsection.to_string()));
+            continue;
+        }
+
+        if let (Some(current), Some((key, value))) = (&current, line.split_once('=')) {
+            config.entry(current.clone()).or_default().insert(
+                key.trim().to_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            );
+        }
+    }
+
+    config
+}
+
+#[derive(Debug, PartialEq)]
+struct IndexEntry {
+    mtime_secs: u32,
+    mtime_nanos: u32,
+    size: u32,
+    path: String,
+}
+
+/// Supports index versions 2 to 4, skipping submodules and sparse entries.
+fn parse_index(index: &[u8]) -> Result<Option<Vec<IndexEntry>>> {
+    let read_u32 = |offset: usize| -> Result<u32> {
+        let bytes = index
+            .get(offset..offset + 4)
+            .context("Truncated git index")?;
+        Ok(u32::from_be_bytes(bytes.try_into()?))
+    };
+
+    if index.get(..4) != Some(b"DIRC") {
+        anyhow::bail!("Not a git index");
+    }
+    let version = read_u32(4)?;
+    if !(2..=4).contains(&version) {
+        anyhow::bail!("Unsupported git index version {version}");
+    }
+    let count = read_u32(8)? as usize;
+    if count > MAX_INDEX_ENTRIES {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut offset = 12;
+    let mut previous_path = Vec::<u8>::new();
+    for _ in 0..count {
+        let start = offset;
+        let mtime_secs = read_u32(start + 8)?;
+        let mtime_nanos = read_u32(start + 12)?;
+        let mode = read_u32(start + 24)?;
+        let size = read_u32(start + 36)?;
+        // 40 bytes of stat data then a 20 bytes object id
+        let flags_offset = start + 60;
+        let flags = u16::from_be_bytes(
+            index
+                .get(flags_offset..flags_offset + 2)
+                .context("Truncated git index")?
+                .try_into()?,
+        );
+        offset = flags_offset + 2;
+
+        let mut skip_worktree = false;
+        if flags & 0x4000 != 0 {
+            let extended = u16::from_be_bytes(
+                index
+                    .get(offset..offset + 2)
+                    .context("Truncated git index")?
+                    .try_into()?,
+            );
+            skip_worktree = extended & 0x4000 != 0;
+            offset += 2;
+        }
+
+        let path = if version == 4 {
+            let (strip, read) = read_varint(&index[offset..]).context("Truncated git index")?;
+            offset += read;
+            let suffix_end = offset + nul_position(&index[offset..])?;
+            let mut path = previous_path[..previous_path.len().saturating_sub(strip)].to_vec();
+            path.extend_from_slice(&index[offset..suffix_end]);
+            offset = suffix_end + 1;
+            path
+        } else {
+            let path_end = offset + nul_position(&index[offset..])?;
+            let path = index[offset..path_end].to_vec();
+            // entries are padded with 1 to 8 NUL bytes to a multiple of 8
+            let entry_length = path_end - start;
+            offset = start + (entry_length + 8) / 8 * 8;
+            path
+        };
+        previous_path = path.clone();
+
+        let is_submodule = mode & 0o170000 == 0o160000;
+        if !is_submodule && !skip_worktree {
+            entries.push(IndexEntry {
+                mtime_secs,
+                mtime_nanos,
+                size,
+                path: String::from_utf8_lossy(&path).to_string(),
+            });
+        }
+    }
+
+    Ok(Some(entries))
+}
+
+fn nul_position(bytes: &[u8]) -> Result<usize> {
+    bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .context("Truncated git index")
+}
+
+/// The offset encoding used by index v4, returns the value and how many bytes were read.
+fn read_varint(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut read = 0;
+    let mut byte = *bytes.first()?;
+    let mut value = (byte & 0x7f) as usize;
+    while byte & 0x80 != 0 {
+        read += 1;
+        byte = *bytes.get(read)?;
+        value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+    }
+    Some((value, read + 1))
+}
+
+fn is_modified(entry: &IndexEntry, path: &Path) -> bool {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return true;
+    };
+    if metadata.len() as u32 != entry.size {
+        return true;
+    }
+
+    let Ok(modified) = metadata
+        .modified()
+        .map(|modified| modified.duration_since(UNIX_EPOCH).unwrap_or_default())
+    else {
+        return false;
+    };
+    // some platforms don't record nanoseconds in the index
+    modified.as_secs() as u32 != entry.mtime_secs
+        || (entry.mtime_nanos != 0 && modified.subsec_nanos() != entry.mtime_nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_context::TempDir;
+
+    fn index_entry(path: &str, size: u32, mtime: std::time::Duration) -> Vec<u8> {
+        let mut entry = vec![];
+        for value in [
+            0,
+            0,
+            mtime.as_secs() as u32,
+            mtime.subsec_nanos(),
+            0,
+            0,
+            0o100644,
+            0,
+            0,
+            size,
+        ] {
+            entry.extend_from_slice(&u32::to_be_bytes(value));
+        }
+        entry.extend_from_slice(&[0; 20]);
+        entry.extend_from_slice(&u16::to_be_bytes(path.len() as u16));
+        entry.extend_from_slice(path.as_bytes());
+        let padding = 8 - (entry.len() % 8);
+        entry.extend(std::iter::repeat_n(0, padding));
+        entry
+    }
+
+    fn index(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut index = b"DIRC".to_vec();
+        index.extend_from_slice(&u32::to_be_bytes(2));
+        index.extend_from_slice(&u32::to_be_bytes(entries.len() as u32));
+        for entry in entries {
+            index.extend_from_slice(entry);
+        }
+        index.extend_from_slice(&[0; 20]);
+        index
+    }
+
+    fn mtime(dir: &TempDir, path: &str) -> std::time::Duration {
+        std::fs::metadata(dir.0.join(path))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+    }
+
+    #[test]
+    fn describes_a_repository() {
+        let repo = TempDir::new("git-repo");
+        repo.write(".git/HEAD", b"ref: refs/heads/feature/login\n");
+        repo.write(
+            ".git/config",
+            br#"[core]
+	bare = false
+[remote "origin"]
+	url = git@github.com:ZibanPirate/hey-cli.git
+[remote "upstream"]
+	url = https://github.com/ZibanPirate/hey-cli.git
+[branch "feature/login"]
+	remote = origin
+	merge = refs/heads/login
+"#,
+        );
+        repo.write(".git/MERGE_HEAD", b"0123456789abcdef\n");
+        repo.write("src/main.rs", b"fn main() {}\n");
+        repo.write("README.md", b"# hey\n");
+        let index = index(&[
+            index_entry("README.md", 6, mtime(&repo, "README.md")),
+            index_entry("deleted.txt", 3, mtime(&repo, "README.md")),
+            index_entry("src/main.rs", 13, mtime(&repo, "src/main.rs")),
+        ]);
+        repo.write(".git/index", &index);
+
+        let section = describe(&repo.0.join("src")).unwrap();
+        assert_eq!(section["branch"], "feature/login");
+        assert_eq!(section["upstream"], "origin/login");
+        let mut remotes = section["remotes"].split(", ").collect::<Vec<_>>();
+        remotes.sort();
+        assert_eq!(remotes, vec!["origin", "upstream"]);
+        assert_eq!(section["in_progress"], "merge");
+        assert_eq!(section["state"], "possibly dirty");
+        assert_eq!(section["possibly_modified_files"], "1");
+
+        repo.write("deleted.txt", b"abc");
+        let index = self::index(&[
+            index_entry("README.md", 6, mtime(&repo, "README.md")),
+            index_entry("deleted.txt", 3, mtime(&repo, "deleted.txt")),
+        ]);
+        repo.write(".git/index", &index);
+        std::fs::remove_file(repo.0.join(".git/MERGE_HEAD")).unwrap();
+
+        let section = describe(&repo.0).unwrap();
+        assert_eq!(section["state"], "clean");
+        assert!(!section.contains_key("in_progress"));
+    }
+
+    #[test]
+    fn detached_head_and_worktrees() {
+        let repo = TempDir::new("git-worktree");
+        repo.write(".git/HEAD", b"ref: refs/heads/main\n");
+        repo.write(
+            ".git/config",
+            b"[branch \"main\"]\n\tremote = .\n\tmerge = refs/heads/dev\n",
+        );
+        repo.write(
+            ".git/worktrees/hotfix/HEAD",
+            b"9fceb02d0ae598e95dc970b74767f19372d61af8\n",
+        );
+        repo.write(".git/worktrees/hotfix/commondir", b"../..\n");
+        repo.write("hotfix/.git", b"gitdir: ../.git/worktrees/hotfix\n");
+        repo.write(".git/worktrees/hotfix/rebase-merge/head-name", b"x");
+
+        let section = describe(&repo.0.join("hotfix")).unwrap();
+        assert_eq!(section["branch"], "detached at 9fceb02");
+        assert_eq!(section["in_progress"], "rebase");
+        assert!(!section.contains_key("state"));
+
+        let section = describe(&repo.0).unwrap();
+        assert_eq!(section["upstream"], "dev");
+    }
+
+    #[test]
+    fn outside_a_repository() {
+        let dir = TempDir::new("git-none");
+        assert!(describe(&dir.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_index_v4_paths() {
+        let mut index = b"DIRC".to_vec();
+        index.extend_from_slice(&u32::to_be_bytes(4));
+        index.extend_from_slice(&u32::to_be_bytes(2));
+        for (strip, suffix) in [(0_u8, "src/lib.rs"), (6, "main.rs")] {
+            for value in [0, 0, 1, 0, 0, 0, 0o100644, 0, 0, 7] {
+                index.extend_from_slice(&u32::to_be_bytes(value));
+            }
+            index.extend_from_slice(&[0; 20]);
+            index.extend_from_slice(&u16::to_be_bytes(10));
+            index.push(strip);
+            index.extend_from_slice(suffix.as_bytes());
+            index.push(0);
+        }
+
+        let paths = parse_index(&index)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["src/lib.rs", "src/main.rs"]);
+        assert_eq!(read_varint(&[0x80, 0x00]), Some((128, 2)));
+    }
+}
@@ -1,40 +1,349 @@
+mod containers;
+mod cwd;
+mod external;
+mod general;
+mod git;
+mod project;
+mod shell_history;
+pub mod tools;
+
 use crate::{
     call_server::CallServer,
-    utils::{PortTrait, State},
+    config::{Config, ContextConfig, LocalConfig},
+    redact::Redactor,
+    utils::{PortTrait, ShellName, State},
 };
 use anyhow::Result;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, mpsc},
+    time::{Duration, Instant},
+};
+
+pub type ContextSection = HashMap<String, String>;
+
+/// The section holding the `[context]` of `.hey.toml`, see [`LocalConfig`].
+const LOCAL_SECTION: &str = "local";
+
+/// Contributes one named section to the context sent along with the ask.
+pub trait ContextProvider: Send {
+    /// Also the name of the section, and the key used to toggle it in `[context.providers]`.
+    fn name(&self) -> &str;
+    /// Providers reading anything personal are opt-in.
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+    /// An empty section is left out of the context.
+    fn provide(&self) -> Result<ContextSection>;
+}
 
 pub struct GenerateContext {
     pub ask: String,
+    pub shell: ShellName,
+    pub config: Config,
 }
 
 impl GenerateContext {
-    pub fn new(ask: String) -> Self {
-        Self { ask }
+    pub fn new(ask: String, shell: ShellName, config: Config) -> Self {
+        Self { ask, shell, config }
     }
 }
 
 impl State<CallServer> for GenerateContext {
-    async fn next(self, _: &impl PortTrait) -> Result<CallServer> {
-        let mut general_context = HashMap::new();
-        let info = os_info::get();
+    async fn next(self, port: &impl PortTrait) -> Result<CallServer> {
+        let local = LocalConfig::load(port, &std::env::current_dir()?)?;
+        let context = generate_context(&self.config.context, Some(self.shell), &local.context)?;
 
-        general_context.insert("os_type".to_string(), info.os_type().to_string());
-        general_context.insert("os_version".to_string(), info.version().to_string());
-        general_context.insert("os_bitness".to_string(), info.bitness().to_string());
-        if let Some(arch) = info.architecture() {
-            general_context.insert("os_architecture".to_string(), arch.to_string());
+        let mut instructions = local.instructions();
+        if !instructions.is_empty()
+            && !local.is_trusted(&self.config.projects, dirs::home_dir().as_deref())
+            && let Some(path) = &local.path
+        {
+            port.log(format!(
+                "Note: the instructions of {} were left out, add its directory to `trusted` in `[projects]` of the config to send them",
+                path.display()
+            ));
+            instructions.clear();
         }
 
-        // TODO: generate more context from plugins
-
-        let mut context = HashMap::new();
-        context.insert("general".to_string(), general_context);
-
         Ok(CallServer {
             ask: self.ask,
             context,
+            instructions,
+            shell: self.shell,
+            config: self.config,
         })
     }
 }
+
+/// Every section goes through [`Redactor`], including the ones from plugins and `.hey.toml`.
+/// `shell` is the one the ask comes from, when known, and `local` the `.hey.toml` context,
+/// always added as the `local` section since it's not read by a provider that could time out.
+pub fn generate_context(
+    config: &ContextConfig,
+    shell: Option<ShellName>,
+    local: &ContextSection,
+) -> Result<HashMap<String, ContextSection>> {
+    let redactor = Arc::new(Redactor::new(&config.redact)?);
+
+    let mut providers: Vec<Box<dyn ContextProvider>> = vec![
+        Box::new(general::General),
+        Box::new(git::Git),
+        Box::new(project::Project),
+        Box::new(tools::Tools),
+        Box::new(containers::Containers),
+        Box::new(cwd::Cwd {
+            config: config.cwd.clone(),
+        }),
+        Box::new(shell_history::ShellHistory {
+            shell,
+            max_entries: config.history.max_entries,
+            redactor: redactor.clone(),
+        }),
+    ];
+    for plugin in &config.plugins {
+        if plugin.name == LOCAL_SECTION
+            || providers
+                .iter()
+                .any(|provider| provider.name() == plugin.name)
+        {
+            anyhow::bail!(
+                "Invalid context plugin name: {} is already a context section",
+                plugin.name
+            );
+        }
+        providers.push(Box::new(external::External::new(
+            plugin,
+            Duration::from_millis(config.timeout_ms),
+        )));
+    }
+
+    let mut context = collect_sections(providers, config);
+    if !local.is_empty() {
+        context.insert(LOCAL_SECTION.to_string(), local.clone());
+    }
+    for section in context.values_mut() {
+        for value in section.values_mut() {
+            *value = redactor.redact(value);
+        }
+    }
+    Ok(context)
+}
+
+/// Runs the enabled providers in parallel, leaving out the ones failing or not done within
+/// `timeout_ms`, since a missing section is better than a slow or failed ask.
+fn collect_sections(
+    providers: Vec<Box<dyn ContextProvider>>,
+    config: &ContextConfig,
+) -> HashMap<String, ContextSection> {
+    let deadline = Instant::now() + Duration::from_millis(config.timeout_ms);
+    let (sender, receiver) = mpsc::channel();
+
+    let mut pending = 0;
+    for provider in providers {
+        let enabled = config
+            .providers
+            .get(provider.name())
+            .copied()
+            .unwrap_or(provider.enabled_by_default());
+        if !enabled {
+            continue;
+        }
+
+        pending += 1;
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            let section = provider.provide();
+            let _ = sender.send((provider.name().to_string(), section));
+        });
+    }
+
+    let mut context = HashMap::new();
+    while pending > 0 {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok((name, Ok(section))) => {
+                if !section.is_empty() {
+                    context.insert(name, section);
+                }
+            }
+            Ok((name, Err(error))) => {
+                tracing::warn!("Context provider {name} failed: {error}");
+            }
+            Err(_) => {
+                tracing::warn!("Context providers timed out, {pending} left out");
+                break;
+            }
+        }
+        pending -= 1;
+    }
+
+    context
+}
+
+/// A directory removed once dropped, for providers reading the filesystem.
+#[cfg(test)]
+pub struct TempDir(pub std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "hey-cli-context-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+        let path = self.0.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ContextPlugin;
+
+    struct Fake {
+        name: &'static str,
+        enabled_by_default: bool,
+        delay: Duration,
+        fails: bool,
+    }
+
+    impl Fake {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                enabled_by_default: true,
+                delay: Duration::ZERO,
+                fails: false,
+            }
+        }
+    }
+
+    impl ContextProvider for Fake {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn enabled_by_default(&self) -> bool {
+            self.enabled_by_default
+        }
+        fn provide(&self) -> Result<ContextSection> {
+            std::thread::sleep(self.delay);
+            if self.fails {
+                anyhow::bail!("oops");
+            }
+            Ok(HashMap::from([("from".to_string(), self.name.to_string())]))
+        }
+    }
+
+    #[test]
+    fn collects_enabled_sections_failing_soft() {
+        let providers: Vec<Box<dyn ContextProvider>> = vec![
+            Box::new(Fake::new("general")),
+            Box::new(Fake {
+                fails: true,
+                ..Fake::new("broken")
+            }),
+            Box::new(Fake {
+                delay: Duration::from_secs(5),
+                ..Fake::new("slow")
+            }),
+            Box::new(Fake {
+                enabled_by_default: false,
+                ..Fake::new("opt_in")
+            }),
+            Box::new(Fake {
+                enabled_by_default: false,
+                ..Fake::new("opted_in")
+            }),
+            Box::new(Fake::new("disabled")),
+        ];
+        let config = ContextConfig {
+            timeout_ms: 200,
+            providers: HashMap::from([
+                ("opted_in".to_string(), true),
+                ("disabled".to_string(), false),
+            ]),
+            ..ContextConfig::default()
+        };
+
+        let started = Instant::now();
+        let context = collect_sections(providers, &config);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let mut sections = context.keys().map(String::as_str).collect::<Vec<_>>();
+        sections.sort();
+        assert_eq!(sections, vec!["general", "opted_in"]);
+        assert_eq!(context["general"]["from"], "general");
+    }
+
+    #[test]
+    fn plugins_cannot_replace_sections() {
+        let plugin = |name: &str| ContextPlugin {
+            name: name.to_string(),
+            command: vec!["true".to_string()],
+        };
+        for plugins in [
+            vec![plugin("git")],
+            vec![plugin("local")],
+            vec![plugin("aws"), plugin("aws")],
+        ] {
+            let config = ContextConfig {
+                plugins,
+                ..ContextConfig::default()
+            };
+            assert!(
+                generate_context(&config, None, &ContextSection::new())
+                    .err()
+                    .unwrap()
+                    .to_string()
+                    .ends_with("is already a context section")
+            );
+        }
+    }
+
+    #[test]
+    fn local_context_survives_disabled_providers() {
+        let config = ContextConfig {
+            providers: [
+                "general",
+                "git",
+                "project",
+                "tools",
+                "containers",
+                "cwd",
+                "history",
+            ]
+            .into_iter()
+            .map(|name| (name.to_string(), false))
+            .collect(),
+            ..ContextConfig::default()
+        };
+        let local = ContextSection::from([
+            ("branch".to_string(), "main is protected".to_string()),
+            (
+                "deploy".to_string(),
+                "kamal deploy, token=abc123".to_string(),
+            ),
+        ]);
+
+        let context = generate_context(&config, None, &local).unwrap();
+        assert_eq!(context.len(), 1);
+        assert_eq!(context["local"]["branch"], "main is protected");
+        assert_eq!(context["local"]["deploy"], "kamal deploy, token=<redacted>");
+    }
+}
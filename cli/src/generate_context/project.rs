@@ -0,0 +1,362 @@
+use super::{ContextProvider, ContextSection};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Lists longer than this are cut, eg: a Makefile with hundreds of targets.
+const MAX_LIST_ITEMS: usize = 30;
+/// The whole section is kept under this many characters, leaving out the keys not fitting.
+const MAX_SECTION_LENGTH: usize = 2_000;
+
+/// Build tools of the project containing the current directory.
+pub struct Project;
+
+impl ContextProvider for Project {
+    fn name(&self) -> &str {
+        "project"
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        Ok(describe(&std::env::current_dir()?))
+    }
+}
+
+fn describe(cwd: &Path) -> ContextSection {
+    let mut entries = vec![];
+
+    if let Some(cargo_toml) = nearest(cwd, &["Cargo.toml"]) {
+        entries.extend(cargo(&cargo_toml));
+    }
+    if let Some(package_json) = nearest(cwd, &["package.json"]) {
+        entries.extend(node(&package_json));
+    }
+    if let Some(pyproject) = nearest(cwd, &["pyproject.toml"]) {
+        entries.extend(python(&pyproject));
+    }
+    if let Some(go_mod) = nearest(cwd, &["go.mod"]) {
+        let content = std::fs::read_to_string(go_mod).unwrap_or_default();
+        let module = content
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("module "));
+        if let Some(module) = module {
+            entries.push(("go_module", module.trim().to_string()));
+        }
+    }
+    if let Some(makefile) = nearest(cwd, &["GNUmakefile", "Makefile", "makefile"]) {
+        let content = std::fs::read_to_string(makefile).unwrap_or_default();
+        entries.push(("make_targets", list(make_targets(&content))));
+    }
+    if let Some(justfile) = nearest(cwd, &["justfile", "Justfile", ".justfile"]) {
+        let content = std::fs::read_to_string(justfile).unwrap_or_default();
+        entries.push(("just_recipes", list(just_recipes(&content))));
+    }
+
+    let mut section = ContextSection::new();
+    let mut length = 0;
+    for (key, value) in entries {
+        if value.is_empty() {
+            continue;
+        }
+        if length + key.len() + value.len() > MAX_SECTION_LENGTH {
+            continue;
+        }
+        length += key.len() + value.len();
+        section.insert(key.to_string(), value);
+    }
+    section
+}
+
+/// The closest file with one of `names`, in `cwd` or its ancestors.
+fn nearest(cwd: &Path, names: &[&str]) -> Option<PathBuf> {
+    cwd.ancestors()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+fn cargo(cargo_toml: &Path) -> Vec<(&'static str, String)> {
+    let mut entries = vec![];
+    let Some(manifest) = read_toml(cargo_toml) else {
+        return entries;
+    };
+
+    if let Some(name) = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+    {
+        entries.push(("cargo_package", name.to_string()));
+    }
+
+    // a package can be a member of a workspace further up
+    let workspace = std::iter::once(manifest)
+        .chain(
+            cargo_toml
+                .parent()
+                .and_then(Path::parent)
+                .into_iter()
+                .flat_map(Path::ancestors)
+                .map(|dir| dir.join("Cargo.toml"))
+                .filter(|path| path.is_file())
+                .filter_map(|path| read_toml(&path)),
+        )
+        .find_map(|manifest| manifest.get("workspace").cloned());
+    if let Some(members) = workspace
+        .as_ref()
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array())
+    {
+        let members = members.iter().filter_map(|member| member.as_str());
+        entries.push(("cargo_workspace_members", list(members)));
+    }
+
+    entries
+}
+
+fn node(package_json: &Path) -> Vec<(&'static str, String)> {
+    let mut entries = vec![];
+    let Some(package) = std::fs::read_to_string(package_json)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return entries;
+    };
+
+    if let Some(name) = package.get("name").and_then(|name| name.as_str()) {
+        entries.push(("node_package", name.to_string()));
+    }
+    if let Some(scripts) = package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+    {
+        entries.push(("node_scripts", list(scripts.keys())));
+    }
+
+    // `"packageManager": "pnpm@9.1.0"` wins over lockfiles
+    let package_manager = package
+        .get("packageManager")
+        .and_then(|manager| manager.as_str())
+        .and_then(|manager| manager.split('@').next())
+        .map(str::to_string)
+        .or_else(|| {
+            let lockfiles = [
+                ("pnpm-lock.yaml", "pnpm"),
+                ("yarn.lock", "yarn"),
+                ("bun.lockb", "bun"),
+                ("bun.lock", "bun"),
+                ("package-lock.json", "npm"),
+            ];
+            package_json.parent()?.ancestors().find_map(|dir| {
+                lockfiles
+                    .iter()
+                    .find(|(lockfile, _)| dir.join(lockfile).is_file())
+                    .map(|(_, manager)| manager.to_string())
+            })
+        });
+    if let Some(package_manager) = package_manager {
+        entries.push(("node_package_manager", package_manager));
+    }
+
+    entries
+}
+
+fn python(pyproject: &Path) -> Vec<(&'static str, String)> {
+    let mut entries = vec![];
+    let Some(project) = read_toml(pyproject) else {
+        return entries;
+    };
+
+    let name = project
+        .get("project")
+        .and_then(|project| project.get("name"))
+        .or_else(|| {
+            project
+                .get("tool")
+                .and_then(|tool| tool.get("poetry"))
+                .and_then(|poetry| poetry.get("name"))
+        })
+        .and_then(|name| name.as_str());
+    if let Some(name) = name {
+        entries.push(("python_project", name.to_string()));
+    }
+
+    let dir = pyproject.parent().unwrap_or(Path::new("."));
+    let tool = if dir.join("uv.lock").is_file() {
+        Some("uv")
+    } else if dir.join("poetry.lock").is_file() || has_tool(&project, "poetry") {
+        Some("poetry")
+    } else if dir.join("pdm.lock").is_file() || has_tool(&project, "pdm") {
+        Some("pdm")
+    } else if has_tool(&project, "hatch") {
+        Some("hatch")
+    } else {
+        None
+    };
+    if let Some(tool) = tool {
+        entries.push(("python_tool", tool.to_string()));
+    }
+
+    if let Some(scripts) = project
+        .get("project")
+        .and_then(|project| project.get("scripts"))
+        .and_then(|scripts| scripts.as_table())
+    {
+        entries.push(("python_scripts", list(scripts.keys())));
+    }
+
+    entries
+}
+
+fn has_tool(project: &toml::Table, tool: &str) -> bool {
+    project
+        .get("tool")
+        .and_then(|tools| tools.get(tool))
+        .is_some()
+}
+
+fn read_toml(path: &Path) -> Option<toml::Table> {
+    toml::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// Rule names, without special targets like `.PHONY`, pattern rules, or variables.
+fn make_targets(makefile: &str) -> Vec<&str> {
+    let mut targets = vec![];
+    for line in makefile.lines() {
+        if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') || names.contains('=') {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            let is_special = name.starts_with('.') || name.contains('%') || name.contains('$');
+            if !is_special && !targets.contains(&name) {
+                targets.push(name);
+            }
+        }
+    }
+    targets
+}
+
+/// Recipe names, without settings, aliases, variables, or private recipes.
+fn just_recipes(justfile: &str) -> Vec<&str> {
+    let mut recipes = vec![];
+    for line in justfile.lines() {
+        if line.starts_with(char::is_whitespace) || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+        let Some((header, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') || header.contains(":=") {
+            continue;
+        }
+        let Some(name) = header.trim_start_matches('@').split_whitespace().next() else {
+            continue;
+        };
+        let is_keyword = ["set", "alias", "export", "import", "mod"].contains(&name);
+        if !is_keyword && !name.starts_with('_') && !recipes.contains(&name) {
+            recipes.push(name);
+        }
+    }
+    recipes
+}
+
+fn list<T: AsRef<str>>(items: impl IntoIterator<Item = T>) -> String {
+    let items = items.into_iter().collect::<Vec<_>>();
+    let mut listed = items
+        .iter()
+        .take(MAX_LIST_ITEMS)
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > MAX_LIST_ITEMS {
+        listed.push_str(&format!(", and {} more", items.len() - MAX_LIST_ITEMS));
+    }
+    listed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_context::TempDir;
+
+    #[test]
+    fn rust_workspace_member() {
+        let dir = TempDir::new("project-rust");
+        dir.write(
+            "Cargo.toml",
+            "[workspace]\nresolver = \"3\"\nmembers = [\"cli\", \"server\"]\n",
+        );
+        dir.write("cli/Cargo.toml", "[package]\nname = \"hey-cli-app\"\n");
+        dir.write("cli/src/main.rs", "fn main() {}");
+        dir.write(
+            "Makefile",
+            ".PHONY: test\nVERSION := 1\ntest: build\n\tcargo test\nbuild lint:\n%.o: %.c\n",
+        );
+        dir.write("justfile", "set shell := [\"bash\"]\nalias t := test\n# run it\n@test *args:\n  cargo test\n_hidden:\nrelease version='1': test\n");
+
+        let section = describe(&dir.0.join("cli/src"));
+        assert_eq!(section["cargo_package"], "hey-cli-app");
+        assert_eq!(section["cargo_workspace_members"], "cli, server");
+        assert_eq!(section["make_targets"], "test, build, lint");
+        assert_eq!(section["just_recipes"], "test, release");
+    }
+
+    #[test]
+    fn node_package() {
+        let dir = TempDir::new("project-node");
+        dir.write(
+            "package.json",
+            r#"{"name": "web", "scripts": {"dev": "vite", "test": "vitest"}}"#,
+        );
+        dir.write("pnpm-lock.yaml", "");
+
+        let section = describe(&dir.0);
+        assert_eq!(section["node_package"], "web");
+        assert_eq!(section["node_scripts"], "dev, test");
+        assert_eq!(section["node_package_manager"], "pnpm");
+
+        dir.write(
+            "package.json",
+            r#"{"name": "web", "packageManager": "yarn@4.1.0"}"#,
+        );
+        assert_eq!(describe(&dir.0)["node_package_manager"], "yarn");
+    }
+
+    #[test]
+    fn python_and_go() {
+        let dir = TempDir::new("project-python-go");
+        dir.write(
+            "pyproject.toml",
+            "[project]\nname = \"api\"\n[project.scripts]\nserve = \"api:main\"\n",
+        );
+        dir.write("uv.lock", "");
+        dir.write("go.mod", "module github.com/ZibanPirate/tools\n\ngo 1.22\n");
+
+        let section = describe(&dir.0);
+        assert_eq!(section["python_project"], "api");
+        assert_eq!(section["python_tool"], "uv");
+        assert_eq!(section["python_scripts"], "serve");
+        assert_eq!(section["go_module"], "github.com/ZibanPirate/tools");
+    }
+
+    #[test]
+    fn lists_and_section_are_capped() {
+        let targets = (0..100)
+            .map(|index| format!("target_{index}:\n"))
+            .collect::<String>();
+        let dir = TempDir::new("project-capped");
+        dir.write("Makefile", &targets);
+        dir.write(
+            "go.mod",
+            format!("module {}\n", "a".repeat(MAX_SECTION_LENGTH)),
+        );
+
+        let section = describe(&dir.0);
+        assert!(section["make_targets"].ends_with("target_29, and 70 more"));
+        assert!(!section.contains_key("go_module"));
+        assert!(section.values().map(String::len).sum::<usize>() <= MAX_SECTION_LENGTH);
+    }
+}
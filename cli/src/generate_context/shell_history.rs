@@ -0,0 +1,274 @@
+use super::{ContextProvider, ContextSection};
+use crate::{redact::Redactor, utils::ShellName};
+use anyhow::Result;
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Only the end of history files is read, they can grow to megabytes.
+const MAX_READ_BYTES: u64 = 256 * 1024;
+/// Longer commands are cut, eg: a pasted script.
+const MAX_COMMAND_LENGTH: usize = 300;
+
+/// The last commands of the active shell, so follow-up asks like "same thing but for staging"
+/// make sense. Opt-in since history is as personal as it gets.
+///
+/// Read from the history file, so commands of the current session only show up once the shell
+/// wrote them, which bash does on exit unless `PROMPT_COMMAND='history -a'`.
+pub struct ShellHistory {
+    pub shell: Option<ShellName>,
+    pub max_entries: usize,
+    pub redactor: Arc<Redactor>,
+}
+
+impl ContextProvider for ShellHistory {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        let Some(shell) = self.shell else {
+            return Ok(ContextSection::new());
+        };
+        let Some(path) = history_file(shell, std::env::var_os("HISTFILE").map(PathBuf::from))
+        else {
+            return Ok(ContextSection::new());
+        };
+        let content = read_tail(&path)?;
+        Ok(describe(
+            parse(shell, &content),
+            self.max_entries,
+            &self.redactor,
+        ))
+    }
+}
+
+fn describe(commands: Vec<String>, max_entries: usize, redactor: &Redactor) -> ContextSection {
+    let mut recent = commands
+        .into_iter()
+        .rev()
+        .filter(|command| !command.trim().is_empty() && !redactor.is_secret(command))
+        .map(|command| {
+            let command = command.trim().replace('\n', "\\n");
+            match command.char_indices().nth(MAX_COMMAND_LENGTH) {
+                Some((index, _)) => format!("{}…", &command[..index]),
+                None => command,
+            }
+        })
+        .collect::<Vec<_>>();
+    recent.dedup();
+    recent.truncate(max_entries);
+    recent.reverse();
+
+    let mut section = ContextSection::new();
+    if !recent.is_empty() {
+        section.insert("recent_commands".to_string(), recent.join("\n"));
+    }
+    section
+}
+
+fn history_file(shell: ShellName, histfile: Option<PathBuf>) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    match shell {
+        ShellName::Fish => {
+            let data_home = match std::env::var_os("XDG_DATA_HOME") {
+                Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+                _ => home.join(".local/share"),
+            };
+            Some(data_home.join("fish/fish_history"))
+        }
+        ShellName::Zsh => Some(histfile.unwrap_or_else(|| home.join(".zsh_history"))),
+        ShellName::Bash => Some(histfile.unwrap_or_else(|| home.join(".bash_history"))),
+        _ => None,
+    }
+}
+
+/// The last [`MAX_READ_BYTES`], starting at a whole line.
+fn read_tail(path: &Path) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let length = file.metadata()?.len();
+    let start = length.saturating_sub(MAX_READ_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut content = vec![];
+    file.read_to_end(&mut content)?;
+    if start > 0 {
+        let first_line_end = content.iter().position(|&byte| byte == b'\n');
+        content.drain(..first_line_end.map_or(content.len(), |end| end + 1));
+    }
+    Ok(content)
+}
+
+fn parse(shell: ShellName, content: &[u8]) -> Vec<String> {
+    match shell {
+        ShellName::Fish => fish_commands(&String::from_utf8_lossy(content)),
+        ShellName::Zsh => zsh_commands(&unmetafy(content)),
+        ShellName::Bash => bash_commands(&String::from_utf8_lossy(content)),
+        _ => vec![],
+    }
+}
+
+/// `fish_history` is YAML-like, with `\\` and `\n` escaped in `- cmd: ` lines.
+fn fish_commands(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(|command| {
+            let mut unescaped = String::with_capacity(command.len());
+            let mut chars = command.chars();
+            while let Some(char) = chars.next() {
+                match (char, chars.clone().next()) {
+                    ('\\', Some('\\')) => {
+                        chars.next();
+                        unescaped.push('\\');
+                    }
+                    ('\\', Some('n')) => {
+                        chars.next();
+                        unescaped.push('\n');
+                    }
+                    _ => unescaped.push(char),
+                }
+            }
+            unescaped
+        })
+        .collect()
+}
+
+/// Plain lines, or `: <start>:<duration>;<command>` with `EXTENDED_HISTORY`,
+/// multi-line commands have their lines ending with `\`.
+fn zsh_commands(content: &str) -> Vec<String> {
+    let mut commands = vec![];
+    let mut pending: Option<String> = None;
+    for line in content.lines() {
+        let line = match &pending {
+            Some(_) => line,
+            None => strip_extended_prefix(line).unwrap_or(line),
+        };
+        let (line, continues) = match line.strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let command = match pending.take() {
+            Some(command) => command + "\n" + line,
+            None => line.to_string(),
+        };
+        match continues {
+            true => pending = Some(command),
+            false => commands.push(command),
+        }
+    }
+    commands.extend(pending);
+    commands
+}
+
+fn strip_extended_prefix(line: &str) -> Option<&str> {
+    let (metadata, command) = line.strip_prefix(": ")?.split_once(';')?;
+    let (start, duration) = metadata.split_once(':')?;
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    (is_number(start.trim()) && is_number(duration.trim())).then_some(command)
+}
+
+/// Plain lines, preceded by `#<timestamp>` lines when `HISTTIMEFORMAT` is set.
+fn bash_commands(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| {
+            let timestamp = line.strip_prefix('#');
+            !timestamp.is_some_and(|timestamp| {
+                !timestamp.is_empty() && timestamp.bytes().all(|byte| byte.is_ascii_digit())
+            })
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// zsh escapes some bytes of its history file as `0x83` followed by the byte xor `0x20`.
+fn unmetafy(content: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(content.len());
+    let mut iter = content.iter();
+    while let Some(&byte) = iter.next() {
+        match byte {
+            0x83 => bytes.extend(iter.next().map(|next| next ^ 0x20)),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::RedactConfig, generate_context::TempDir};
+
+    #[test]
+    fn fish() {
+        let content = "- cmd: kubectl config use-context prod\n  when: 1700000000\n- cmd: echo a\\\\b\\nc\n  when: 1700000001\n  paths:\n    - a\n";
+        assert_eq!(
+            fish_commands(content),
+            vec!["kubectl config use-context prod", "echo a\\b\nc"]
+        );
+    }
+
+    #[test]
+    fn zsh() {
+        let mut content = b": 1700000000:0;git status\nls -la\n: 1700000002:3;for f in *; do\\\n  echo $f\\\ndone\n: 1700000003:0;echo caf".to_vec();
+        // `é` is 0xc3 0xa9, zsh metafies 0xa9 as 0x83 0x89
+        content.extend([0xc3, 0x83, 0x89, b'\n']);
+
+        assert_eq!(
+            zsh_commands(&unmetafy(&content)),
+            vec![
+                "git status",
+                "ls -la",
+                "for f in *; do\n  echo $f\ndone",
+                "echo café"
+            ]
+        );
+    }
+
+    #[test]
+    fn bash() {
+        let content = "#1700000000\nkubectl get pods -n staging\n# a comment\ncd ~\n";
+        assert_eq!(
+            bash_commands(content),
+            vec!["kubectl get pods -n staging", "# a comment", "cd ~"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_last_entries_without_secrets() {
+        let redactor = Redactor::new(&RedactConfig::default()).unwrap();
+        let commands = [
+            "ls",
+            "git push",
+            "git push",
+            "export GITHUB_TOKEN=abc",
+            "",
+            "make",
+        ]
+        .map(str::to_string)
+        .to_vec();
+
+        let section = describe(commands.clone(), 10, &redactor);
+        assert_eq!(section["recent_commands"], "ls\ngit push\nmake");
+        let section = describe(commands, 2, &redactor);
+        assert_eq!(section["recent_commands"], "git push\nmake");
+        assert!(describe(vec![], 10, &redactor).is_empty());
+    }
+
+    #[test]
+    fn reads_whole_lines_from_the_end() {
+        let dir = TempDir::new("shell-history");
+        let first = "a".repeat(MAX_READ_BYTES as usize);
+        dir.write(".bash_history", format!("{first}\necho last\n"));
+
+        let content = read_tail(&dir.0.join(".bash_history")).unwrap();
+        assert_eq!(content, b"echo last\n");
+    }
+}
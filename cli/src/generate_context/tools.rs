@@ -0,0 +1,273 @@
+use super::{ContextProvider, ContextSection};
+use anyhow::Result;
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Tools the model tends to suggest, whether they're installed or not.
+const TOOLS: &[&str] = &[
+    "7z",
+    "apt",
+    "awk",
+    "bat",
+    "batcat",
+    "brew",
+    "btop",
+    "bun",
+    "cargo",
+    "curl",
+    "deno",
+    "delta",
+    "dnf",
+    "docker",
+    "duf",
+    "dust",
+    "eza",
+    "exa",
+    "fd",
+    "fdfind",
+    "ffmpeg",
+    "fzf",
+    "gawk",
+    "gh",
+    "git",
+    "go",
+    "gsed",
+    "gtar",
+    "helm",
+    "htop",
+    "hyperfine",
+    "ifconfig",
+    "ip",
+    "jq",
+    "journalctl",
+    "just",
+    "kubectl",
+    "lsof",
+    "magick",
+    "make",
+    "nc",
+    "netstat",
+    "node",
+    "npm",
+    "pacman",
+    "pbcopy",
+    "pnpm",
+    "podman",
+    "procs",
+    "python",
+    "python3",
+    "rg",
+    "rsync",
+    "screen",
+    "sd",
+    "ss",
+    "systemctl",
+    "tmux",
+    "tree",
+    "unzip",
+    "watch",
+    "wget",
+    "wl-copy",
+    "xclip",
+    "xdg-open",
+    "yarn",
+    "yq",
+    "zip",
+];
+
+/// Which of [`TOOLS`] are on `PATH`, and whether `ls` and `sed` are GNU, BSD or BusyBox flavoured.
+pub struct Tools;
+
+impl ContextProvider for Tools {
+    fn name(&self) -> &str {
+        "tools"
+    }
+
+    fn provide(&self) -> Result<ContextSection> {
+        let paths = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default();
+        Ok(describe(&paths))
+    }
+}
+
+fn describe(paths: &[PathBuf]) -> ContextSection {
+    let mut section = ContextSection::new();
+
+    let available = TOOLS
+        .iter()
+        .filter(|tool| find_in_path(paths, tool).is_some())
+        .copied()
+        .collect::<Vec<_>>();
+    if !available.is_empty() {
+        section.insert("available".to_string(), available.join(", "));
+    }
+
+    for (key, tool) in [("coreutils", "ls"), ("sed", "sed")] {
+        if let Some(path) = find_in_path(paths, tool) {
+            section.insert(key.to_string(), flavour(&path).to_string());
+        }
+    }
+
+    section
+}
+
+pub fn find_in_path(paths: &[PathBuf], tool: &str) -> Option<PathBuf> {
+    paths
+        .iter()
+        .flat_map(|dir| executable_names(tool).map(move |name| dir.join(name)))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(windows)]
+fn executable_names(tool: &str) -> impl Iterator<Item = String> {
+    ["exe", "cmd", "bat", "com"]
+        .into_iter()
+        .map(move |extension| format!("{tool}.{extension}"))
+}
+
+#[cfg(not(windows))]
+fn executable_names(tool: &str) -> impl Iterator<Item = String> {
+    std::iter::once(tool.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// BSD tools don't know `--version`, so anything not saying otherwise is BSD.
+fn flavour(path: &Path) -> &'static str {
+    let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if resolved.file_name().is_some_and(|name| name == "busybox") {
+        return "busybox";
+    }
+
+    let output = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output();
+    let version = match &output {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => String::new(),
+    };
+    flavour_from_version(&version)
+}
+
+fn flavour_from_version(version: &str) -> &'static str {
+    if version.contains("BusyBox") {
+        "busybox"
+    } else if version.contains("GNU") {
+        "gnu"
+    } else if version.contains("uutils") {
+        "uutils"
+    } else if version.contains("toybox") {
+        "toybox"
+    } else {
+        "bsd"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_context::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_executables_in_path_order() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("tools");
+        for path in ["bin/rg", "bin/jq", "local/jq"] {
+            dir.write(path, "#!/bin/sh\n");
+        }
+        dir.write("bin/fd", "not executable");
+        for path in ["bin/rg", "bin/jq", "local/jq"] {
+            let path = dir.0.join(path);
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let paths = vec![
+            dir.0.join("local"),
+            dir.0.join("missing"),
+            dir.0.join("bin"),
+        ];
+        assert_eq!(find_in_path(&paths, "jq"), Some(dir.0.join("local/jq")));
+        assert_eq!(find_in_path(&paths, "rg"), Some(dir.0.join("bin/rg")));
+        assert_eq!(find_in_path(&paths, "fd"), None);
+
+        let section = describe(&paths);
+        assert_eq!(section["available"], "jq, rg");
+        assert!(!section.contains_key("coreutils"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn survives_redaction_on_generic_usernames() {
+        use crate::{config::RedactConfig, redact::Redactor};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("tools-redacted");
+        for path in ["bin/node", "bin/git", "bin/npm"] {
+            dir.write(path, "#!/bin/sh\n");
+            let path = dir.0.join(path);
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let section = describe(&[dir.0.join("bin")]);
+        // the user of the official node images, and of many CI runners
+        let redactor = Redactor::with_identity(
+            &RedactConfig::default(),
+            Some("node".to_string()),
+            Some("3f9c2a1b7d4e".to_string()),
+            "",
+        )
+        .unwrap();
+        let redacted = section
+            .iter()
+            .map(|(key, value)| (key.clone(), redactor.redact(value)))
+            .collect::<ContextSection>();
+        assert_eq!(redacted, section);
+        assert_eq!(redacted["available"], "git, node, npm");
+    }
+
+    #[test]
+    fn tells_flavours_apart() {
+        assert_eq!(
+            flavour_from_version("ls (GNU coreutils) 9.4\nCopyright (C) 2023"),
+            "gnu"
+        );
+        assert_eq!(
+            flavour_from_version("sed (GNU sed) 4.9\nPackaged by Debian"),
+            "gnu"
+        );
+        assert_eq!(
+            flavour_from_version("BusyBox v1.36.1 (2023-11-07) multi-call binary."),
+            "busybox"
+        );
+        assert_eq!(
+            flavour_from_version("ls (uutils coreutils) 0.0.27"),
+            "uutils"
+        );
+        assert_eq!(
+            flavour_from_version("ls: unrecognized option `--version'\nusage: ls [-@ABC]"),
+            "bsd"
+        );
+        assert_eq!(flavour_from_version(""), "bsd");
+    }
+}
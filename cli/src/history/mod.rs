@@ -0,0 +1,326 @@
+use crate::{
+    config::HistoryConfig,
+    utils::{FilePort, PortTrait, ShellName, State},
+};
+use anyhow::Result;
+use hey_cli_common::stable_hash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Listing without a search only shows the most recent entries.
+const MAX_LISTED_ENTRIES: usize = 20;
+/// Enough to hold the last entry, read to know the next id.
+const MAX_TAIL_BYTES: u64 = 16 * 1024;
+
+/// One line of `history.jsonl`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub id: u64,
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub ask: String,
+    pub context_hash: String,
+    pub command: String,
+    pub shell: String,
+    pub cwd: String,
+}
+
+/// Where an injected command came from, recorded once it's injected.
+pub struct Origin {
+    pub ask: String,
+    pub context_hash: String,
+    pub shell: ShellName,
+}
+
+/// Lists past asks, optionally only the ones containing `search`.
+pub struct History {
+    pub search: Option<String>,
+}
+
+impl State<()> for History {
+    async fn next(self, port: &impl PortTrait) -> Result<()> {
+        let entries = read_entries(port)?;
+        if entries.is_empty() {
+            port.log("No history yet");
+            return Ok(());
+        }
+
+        let entries = match &self.search {
+            Some(search) => search_entries(&entries, search),
+            None => {
+                let skip = entries.len().saturating_sub(MAX_LISTED_ENTRIES);
+                entries.iter().skip(skip).collect()
+            }
+        };
+        if entries.is_empty() {
+            port.log("No matching history entry");
+            return Ok(());
+        }
+
+        for entry in entries {
+            port.log(format!(
+                "{:>4}  {}  {}",
+                entry.id,
+                format_timestamp(entry.timestamp),
+                entry.ask
+            ));
+            port.log(format!("      {}", entry.command));
+        }
+        port.log("Use `hey --replay <id>` to insert a command again");
+
+        Ok(())
+    }
+}
+
+/// `$XDG_DATA_HOME/hey-cli/history.jsonl`, defaulting to `~/.local/share/hey-cli/history.jsonl`.
+pub fn history_path() -> Option<PathBuf> {
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+        _ => dirs::home_dir()?.join(".local/share"),
+    };
+    Some(data_dir.join("hey-cli/history.jsonl"))
+}
+
+pub fn read_entries(files: &dyn FilePort) -> Result<Vec<HistoryEntry>> {
+    let Some(path) = history_path() else {
+        return Ok(vec![]);
+    };
+    Ok(parse_entries(&files.read_file(&path)?.unwrap_or_default()))
+}
+
+/// Appends the injected `command` to the history file.
+pub fn record(
+    files: &dyn FilePort,
+    origin: Origin,
+    command: &str,
+    config: &HistoryConfig,
+) -> Result<()> {
+    let Some(path) = history_path() else {
+        return Ok(());
+    };
+    let entry = HistoryEntry {
+        id: 0,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+        ask: origin.ask,
+        context_hash: origin.context_hash,
+        command: command.to_string(),
+        shell: origin.shell.to_string(),
+        cwd: std::env::current_dir()
+            .map(|cwd| cwd.display().to_string())
+            .unwrap_or_default(),
+    };
+    append(files, &path, entry, config.max_entries)
+}
+
+/// Gives `entry` the id following the last one, only reading the end of the file.
+///
+/// Every `max_entries` appends the file is rewritten with the last `max_entries` entries,
+/// so it holds at most twice as many without being read in full on every append.
+fn append(
+    files: &dyn FilePort,
+    path: &Path,
+    mut entry: HistoryEntry,
+    max_entries: usize,
+) -> Result<()> {
+    let tail = files
+        .read_file_tail(path, MAX_TAIL_BYTES)?
+        .unwrap_or_default();
+    let last_id = match parse_entries(&tail).last() {
+        Some(last) => Some(last.id),
+        // an empty file, or a last entry bigger than the tail or corrupted
+        None => {
+            let content = files.read_file(path)?.unwrap_or_default();
+            parse_entries(&content).last().map(|last| last.id)
+        }
+    };
+    entry.id = last_id.map_or(1, |id| id + 1);
+    files.append_to_file(path, &serde_json::to_string(&entry)?)?;
+
+    let max_entries = max_entries.max(1);
+    if entry.id.is_multiple_of(max_entries as u64) {
+        let entries = parse_entries(&files.read_file(path)?.unwrap_or_default());
+        let skip = entries.len().saturating_sub(max_entries);
+        let kept = entries[skip..]
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        files.overwrite_file(path, &(kept.join("\n") + "\n"))?;
+    }
+    Ok(())
+}
+
+/// Skips lines that can't be parsed, so a corrupted line doesn't lose the whole history.
+fn parse_entries(content: &str) -> Vec<HistoryEntry> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Case-insensitive match on the ask or the command.
+fn search_entries<'a>(entries: &'a [HistoryEntry], search: &str) -> Vec<&'a HistoryEntry> {
+    let search = search.to_lowercase();
+    entries
+        .iter()
+        .filter(|entry| {
+            entry.ask.to_lowercase().contains(&search)
+                || entry.command.to_lowercase().contains(&search)
+        })
+        .collect()
+}
+
+/// Independent of the keys order.
+pub fn context_hash(context: &HashMap<String, HashMap<String, String>>) -> String {
+    let sorted = context
+        .iter()
+        .map(|(section, values)| (section, values.iter().collect::<BTreeMap<_, _>>()))
+        .collect::<BTreeMap<_, _>>();
+    stable_hash(&serde_json::to_string(&sorted).unwrap_or_default())
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let minutes_of_day = timestamp % 86_400 / 60;
+
+    // civil from days, see: https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        minutes_of_day / 60,
+        minutes_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryFiles;
+
+    fn entry(id: u64, ask: &str, command: &str) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            timestamp: 0,
+            ask: ask.to_string(),
+            context_hash: "0".to_string(),
+            command: command.to_string(),
+            shell: "fish".to_string(),
+            cwd: "/tmp".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_entries_skipping_invalid_lines() {
+        let content = [
+            serde_json::to_string(&entry(1, "list files", "ls")).unwrap(),
+            "not json".to_string(),
+            "".to_string(),
+            serde_json::to_string(&entry(2, "disk usage", "du -sh .")).unwrap(),
+        ]
+        .join("\n");
+
+        assert_eq!(
+            parse_entries(&content),
+            vec![
+                entry(1, "list files", "ls"),
+                entry(2, "disk usage", "du -sh .")
+            ]
+        );
+    }
+
+    #[test]
+    fn appends_with_the_next_id_and_rotates() {
+        let files = MemoryFiles::default();
+        let path = Path::new("/data/hey-cli/history.jsonl");
+        let ids = || {
+            parse_entries(&files.read_file(path).unwrap().unwrap())
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>()
+        };
+
+        for _ in 0..5 {
+            append(&files, path, entry(0, "list files", "ls"), 3).unwrap();
+        }
+        assert_eq!(ids(), vec![1, 2, 3, 4, 5]);
+
+        append(&files, path, entry(0, "list files", "ls"), 3).unwrap();
+        assert_eq!(ids(), vec![4, 5, 6]);
+
+        // the last entry doesn't fit in the tail
+        let long = "a".repeat(MAX_TAIL_BYTES as usize);
+        append(&files, path, entry(0, "long", &long), 3).unwrap();
+        append(&files, path, entry(0, "list files", "ls"), 3).unwrap();
+        assert_eq!(ids(), vec![4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn searches_asks_and_commands() {
+        let entries = vec![
+            entry(1, "list files", "ls -la"),
+            entry(2, "Disk usage", "du -sh ."),
+            entry(3, "show big files", "find . -size +100M"),
+        ];
+
+        let ids = |search| {
+            search_entries(&entries, search)
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("files"), vec![1, 3]);
+        assert_eq!(ids("disk"), vec![2]);
+        assert_eq!(ids("-SIZE"), vec![3]);
+        assert_eq!(ids("docker"), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn context_hash_ignores_keys_order() {
+        let context = |pairs: &[(&str, &str)]| {
+            let mut context = HashMap::new();
+            context.insert(
+                "general".to_string(),
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            );
+            context
+        };
+
+        let hash = context_hash(&context(&[("os_type", "Mac OS"), ("os_bitness", "64-bit")]));
+        assert_eq!(
+            hash,
+            context_hash(&context(&[("os_bitness", "64-bit"), ("os_type", "Mac OS")]))
+        );
+        assert_ne!(hash, context_hash(&context(&[("os_type", "Windows")])));
+        assert_eq!(hash.len(), 16);
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1_792_332_245), "2026-10-18 14:04");
+    }
+}
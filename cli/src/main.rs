@@ -1,11 +1,20 @@
+mod cache;
 mod call_server;
 mod check_ask;
 mod check_setup;
+mod config;
+mod explain_command;
 mod generate_context;
+mod history;
 mod parse_args;
+mod pick_prompt;
 mod prompt;
+mod redact;
+mod replay;
 mod reset;
+mod safety;
 mod setup_script;
+mod shell_integration;
 mod utils;
 mod what_to_do;
 
@@ -77,6 +86,16 @@ async fn run(args: ParseArgs, port: &Mutex<Port>) -> Result<()> {
                 port.log(everything_after_prompt);
             }
         },
+        WhatToDoAfterParseArgs::ExplainCommand(explain_command) => {
+            explain_command.next(port).await?;
+        }
+        WhatToDoAfterParseArgs::History(history) => {
+            history.next(port).await?;
+        }
+        WhatToDoAfterParseArgs::Replay(replay) => {
+            let prompt = replay.next(port).await?;
+            prompt.next(port).await?;
+        }
         WhatToDoAfterParseArgs::CheckSetup(check_setup) => {
             let what_to_do = check_setup.next(port).await?;
             match what_to_do {
@@ -86,8 +105,10 @@ async fn run(args: ParseArgs, port: &Mutex<Port>) -> Result<()> {
                 WhatToDoAfterCheckSetup::CheckAsk(check_ask) => {
                     let generate_context = check_ask.next(port).await?;
                     let call_server = generate_context.next(port).await?;
-                    let prompt = call_server.next(port).await?;
-                    prompt.next(port).await?;
+                    let pick_prompt = call_server.next(port).await?;
+                    if let Some(prompt) = pick_prompt.next(port).await? {
+                        prompt.next(port).await?;
+                    }
                 }
             }
         }
@@ -178,6 +199,34 @@ mod end_to_end_tests {
         );
     }
 
+    #[tokio::test]
+    async fn reset_flag_nothing_installed() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                reset: true,
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            r#"Resetting hey-cli setup for all shells...
+No cleanup needed for fish shell
+No cleanup needed for bash shell
+No cleanup needed for zsh shell
+No cleanup needed for power_shell shell
+No cleanup needed for nushell shell
+No cleanup needed for elvish shell
+No cleanup needed for xonsh shell
+Reset completed successfully
+Please open new terminal session for changes to take effect"#
+        );
+    }
+
     #[tokio::test]
     async fn ask_no_shell_no_supported_shell_listed() {
         let port = Port::new_mutex();
@@ -193,20 +242,13 @@ mod end_to_end_tests {
             &port,
         )
         .await;
-        assert!(res.is_ok());
-        let stdout = port.to_stdout_format();
+        assert!(res.is_err());
         assert_eq!(
-            stdout.into(),
-            r#"Setup script not installed
-Installing setup script for shell: fish
-Installing setup script for shell: bash
-bash shell is not yet supported
-Installing setup script for shell: zsh
-Installing setup script for shell: power_shell
-power_shell shell is not yet supported
-Setup script installed successfully
-Please open new terminal session"#,
+            res.unwrap_err().to_string(),
+            "Could not find a supported shell to set up, supported shells are: fish, bash, zsh, power_shell, nushell, elvish, xonsh"
         );
+        let stdout = port.to_stdout_format();
+        assert_eq!(stdout.into(), "Setup script not installed",);
     }
 
     #[tokio::test]
@@ -225,20 +267,13 @@ Please open new terminal session"#,
         )
         .await;
 
-        assert!(res.is_ok());
-        let stdout = port.to_stdout_format();
+        assert!(res.is_err());
         assert_eq!(
-            stdout.into(),
-            r#"Setup script not installed
-Installing setup script for shell: fish
-Installing setup script for shell: bash
-bash shell is not yet supported
-Installing setup script for shell: zsh
-Installing setup script for shell: power_shell
-power_shell shell is not yet supported
-Setup script installed successfully
-Please open new terminal session"#
+            res.unwrap_err().to_string(),
+            "Could not find a supported shell to set up, supported shells are: fish, bash, zsh, power_shell, nushell, elvish, xonsh"
         );
+        let stdout = port.to_stdout_format();
+        assert_eq!(stdout.into(), "Setup script not installed");
     }
 
     #[tokio::test]
@@ -265,11 +300,6 @@ Please open new terminal session"#
             stdout.into(),
             r#"Setup script outdated
 Installing setup script for shell: fish
-Installing setup script for shell: bash
-bash shell is not yet supported
-Installing setup script for shell: zsh
-Installing setup script for shell: power_shell
-power_shell shell is not yet supported
 Setup script installed successfully
 Please open new terminal session"#
         );
@@ -336,4 +366,226 @@ Please open new terminal session"#
             format!("\nhey-cli-prompt-start\necho \"print working directory\"")
         );
     }
+
+    #[tokio::test]
+    async fn ask_with_bash_shell() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                shell_name: Some("bash".to_string()),
+                setup_version: Some("0.1.0".to_string()),
+                ask: vec![
+                    "print".to_string(),
+                    "working".to_string(),
+                    "directory".to_string(),
+                ],
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            format!("\nhey-cli-prompt-start\necho \"print working directory\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn ask_with_power_shell() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                shell_name: Some("power_shell".to_string()),
+                setup_version: Some("0.1.0".to_string()),
+                ask: vec![
+                    "print".to_string(),
+                    "working".to_string(),
+                    "directory".to_string(),
+                ],
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            format!("\nhey-cli-prompt-start\necho \"print working directory\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn ask_with_nushell_elvish_and_xonsh_shells() {
+        for shell_name in ["nushell", "elvish", "xonsh"] {
+            let port = Port::new_mutex();
+            let res = run(
+                ParseArgs {
+                    shell_name: Some(shell_name.to_string()),
+                    setup_version: Some("0.1.0".to_string()),
+                    ask: vec![
+                        "print".to_string(),
+                        "working".to_string(),
+                        "directory".to_string(),
+                    ],
+                    ..Default::default()
+                },
+                &port,
+            )
+            .await;
+
+            assert!(res.is_ok());
+            let stdout = port.to_stdout_format();
+            assert_eq!(
+                stdout.into(),
+                format!("\nhey-cli-prompt-start\necho \"print working directory\"")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn ask_with_shell_multiple_candidates() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                shell_name: Some("fish".to_string()),
+                setup_version: Some("0.1.0".to_string()),
+                candidates: Some(3),
+                ask: vec![
+                    "print".to_string(),
+                    "working".to_string(),
+                    "directory".to_string(),
+                ],
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            format!("\nhey-cli-prompt-start\necho \"print working directory\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn ask_with_shell_explain() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                shell_name: Some("fish".to_string()),
+                setup_version: Some("0.1.0".to_string()),
+                explain: true,
+                ask: vec![
+                    "print".to_string(),
+                    "working".to_string(),
+                    "directory".to_string(),
+                ],
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            format!(
+                "echo: prints \"print working directory\"\nhey-cli-prompt-start\necho \"print working directory\""
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn what_flag_no_shell() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                what: Some("tar -xzvf foo.tgz -C /opt".to_string()),
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_ok());
+        let stdout = port.to_stdout_format();
+        assert_eq!(
+            stdout.into(),
+            r#"explains "tar -xzvf foo.tgz -C /opt"
+  tar      explains tar
+  -xzvf    explains -xzvf
+  foo.tgz  explains foo.tgz
+  -C       explains -C
+  /opt     explains /opt"#
+        );
+    }
+
+    #[tokio::test]
+    async fn history_flag_no_history() {
+        for ask in [vec![], vec!["search".to_string(), "files".to_string()]] {
+            let port = Port::new_mutex();
+            let res = run(
+                ParseArgs {
+                    history: true,
+                    ask,
+                    ..Default::default()
+                },
+                &port,
+            )
+            .await;
+
+            assert!(res.is_ok());
+            let stdout = port.to_stdout_format();
+            assert_eq!(stdout.into(), "No history yet");
+        }
+    }
+
+    #[tokio::test]
+    async fn history_flag_invalid_subcommand() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                history: true,
+                ask: vec!["delete".to_string()],
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_err());
+        let error = res.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid history command: expected `hey --history` or `hey --history search <text>`"
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_flag_unknown_id() {
+        let port = Port::new_mutex();
+        let res = run(
+            ParseArgs {
+                replay: Some(42),
+                ..Default::default()
+            },
+            &port,
+        )
+        .await;
+
+        assert!(res.is_err());
+        let error = res.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No history entry with id 42, see `hey --history`"
+        );
+    }
 }
@@ -2,6 +2,10 @@
 
 use crate::{
     check_setup::CheckSetup,
+    config::Config,
+    explain_command::ExplainCommand,
+    history::History,
+    replay::Replay,
     reset::Reset,
     utils::{PortTrait, Shell, ShellName, State},
     what_to_do::{WhatToDoAfterParseArgs, WhatToDoAfterParseArgsInternalAction},
@@ -38,13 +42,41 @@ pub struct ParseArgs {
     /// The version of the setup script
     pub setup_version: Option<String>,
 
+    #[arg(long)]
+    /// Server URL to send asks to, overrides the config file
+    pub server: Option<String>,
+
+    #[arg(long)]
+    /// How many commands to choose from, overrides the config file
+    pub candidates: Option<u8>,
+
+    #[arg(long)]
+    /// Explain the generated command
+    pub explain: bool,
+
+    #[arg(long)]
+    /// Ask the server even if the same ask was answered before
+    pub no_cache: bool,
+
+    #[arg(long, value_name = "COMMAND")]
+    /// Explain an existing command instead of generating one
+    pub what: Option<String>,
+
+    #[arg(long)]
+    /// List past asks, or search them with `--history search <text>`
+    pub history: bool,
+
+    #[arg(long, value_name = "ID")]
+    /// Insert a command from the history again, without asking the server
+    pub replay: Option<u64>,
+
     /// Your ask
     #[arg()]
     pub ask: Vec<String>,
 }
 
 impl State<WhatToDoAfterParseArgs> for ParseArgs {
-    async fn next(self, _: &impl PortTrait) -> Result<WhatToDoAfterParseArgs> {
+    async fn next(self, port: &impl PortTrait) -> Result<WhatToDoAfterParseArgs> {
         let shell = match (self.shell_name, self.setup_version) {
             (Some(shell_name), Some(setup_version)) => Some(Shell {
                 setup_version,
@@ -82,9 +114,51 @@ impl State<WhatToDoAfterParseArgs> for ParseArgs {
             });
         }
 
+        if self.history {
+            let search = match self.ask.split_first() {
+                None => None,
+                Some((subcommand, text)) if subcommand == "search" && !text.is_empty() => {
+                    Some(text.join(" "))
+                }
+                Some(_) => {
+                    return Err(anyhow::anyhow!(
+                        "Invalid history command: expected `hey --history` or `hey --history search <text>`"
+                    ));
+                }
+            };
+            return Ok(WhatToDoAfterParseArgs::History(History { search }));
+        }
+
+        let mut config = Config::load(port)?;
+        if let Some(server) = self.server {
+            config.set_server_url(&server);
+        }
+        if let Some(candidates) = self.candidates {
+            config.candidates = candidates;
+        }
+        if self.explain {
+            config.explain = true;
+        }
+        if self.no_cache {
+            config.cache.enabled = false;
+        }
+
+        if let Some(id) = self.replay {
+            return Ok(WhatToDoAfterParseArgs::Replay(Replay { id, config }));
+        }
+
+        if let Some(command) = self.what {
+            return Ok(WhatToDoAfterParseArgs::ExplainCommand(ExplainCommand {
+                command,
+                shell: shell.map(|shell| shell.name),
+                config,
+            }));
+        }
+
         Ok(WhatToDoAfterParseArgs::CheckSetup(CheckSetup {
             shell,
             ask,
+            config,
         }))
     }
 }
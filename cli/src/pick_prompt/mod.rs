@@ -0,0 +1,132 @@
+use crate::{
+    config::Config,
+    history::Origin,
+    prompt::Prompt,
+    utils::{PortTrait, State},
+};
+use anyhow::Result;
+use hey_cli_common::CliPrompt;
+
+pub struct PickPrompt {
+    /// Best first, never empty.
+    pub candidates: Vec<CliPrompt>,
+    pub config: Config,
+    pub origin: Origin,
+}
+
+impl State<Option<Prompt>> for PickPrompt {
+    async fn next(self, port: &impl PortTrait) -> Result<Option<Prompt>> {
+        let index = match self.candidates.len() {
+            1 => Some(0),
+            _ => port.pick_candidate(&self.candidates)?,
+        };
+
+        let Some(candidate) = index.and_then(|index| self.candidates.into_iter().nth(index)) else {
+            port.log("No command picked");
+            return Ok(None);
+        };
+
+        Ok(Some(Prompt {
+            value: candidate.value,
+            explain: candidate.explain,
+            config: self.config,
+            origin: Some(self.origin),
+        }))
+    }
+}
+
+/// Lets the user pick a candidate with arrow or number keys, drawn on stderr
+/// since stdout is captured by the setup script.
+#[cfg(not(test))]
+pub fn run_picker(candidates: &[CliPrompt]) -> Result<Option<usize>> {
+    use crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        queue,
+        style::{Attribute, Print, SetAttribute},
+        terminal::{self, ClearType},
+    };
+    use std::io::Write;
+
+    let mut stderr = std::io::stderr();
+    let mut selected = 0;
+    let draw = |stderr: &mut std::io::Stderr, selected: usize| -> Result<()> {
+        queue!(
+            stderr,
+            Print("Pick a command (↑/↓ or 1-9, enter to confirm, esc to cancel):\r\n")
+        )?;
+        for (index, candidate) in candidates.iter().enumerate() {
+            let marker = if index == selected { ">" } else { " " };
+            let label = match &candidate.label {
+                Some(label) => format!("{label}: "),
+                None => "".to_string(),
+            };
+            if index == selected {
+                queue!(stderr, SetAttribute(Attribute::Bold))?;
+            }
+            queue!(
+                stderr,
+                Print(format!(
+                    "{marker} {}. {label}{}\r\n",
+                    index + 1,
+                    candidate.value
+                )),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        stderr.flush()?;
+        Ok(())
+    };
+    let clear = |stderr: &mut std::io::Stderr| -> Result<()> {
+        queue!(
+            stderr,
+            cursor::MoveToColumn(0),
+            cursor::MoveUp(candidates.len() as u16 + 1),
+            terminal::Clear(ClearType::FromCursorDown)
+        )?;
+        Ok(())
+    };
+
+    terminal::enable_raw_mode()?;
+    let picked = (|| -> Result<Option<usize>> {
+        draw(&mut stderr, selected)?;
+        loop {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Enter => return Ok(Some(selected)),
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(None);
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    selected = selected.checked_sub(1).unwrap_or(candidates.len() - 1);
+                }
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                    selected = (selected + 1) % candidates.len();
+                }
+                KeyCode::Char(digit @ '1'..='9') => {
+                    let index = digit as usize - '1' as usize;
+                    if index < candidates.len() {
+                        return Ok(Some(index));
+                    }
+                }
+                _ => {}
+            }
+
+            clear(&mut stderr)?;
+            draw(&mut stderr, selected)?;
+        }
+    })();
+
+    // leave the terminal as we found it, even when reading keys failed
+    let cleared = clear(&mut stderr).and_then(|_| Ok(stderr.flush()?));
+    terminal::disable_raw_mode()?;
+    cleared?;
+    picked
+}
@@ -1,15 +1,77 @@
 use anyhow::Result;
 
-use crate::utils::{PortTrait, State};
+use crate::{
+    config::{Config, SafetyAction},
+    history::{self, Origin},
+    safety::SafetyAnalyzer,
+    utils::{PortTrait, State},
+};
 
 pub struct Prompt {
     pub value: String,
+    pub explain: Option<String>,
+    pub config: Config,
+    /// Where the command came from, `None` when replayed from the history.
+    pub origin: Option<Origin>,
 }
 
 impl State<()> for Prompt {
     async fn next(self, port: &impl PortTrait) -> Result<()> {
-        port.set_final_prompt(self.value);
+        if let Some(explain) = self.explain {
+            port.log(explain);
+        }
+
+        let findings = SafetyAnalyzer::new(&self.config.safety)?.analyze(&self.value);
+        let inserted = match findings.is_empty() {
+            true => self.value.clone(),
+            false => {
+                port.log("Warning: this command looks dangerous, review it before running it:");
+                for finding in findings {
+                    port.log(format!("  - {finding}"));
+                }
+
+                match self.config.safety.action {
+                    SafetyAction::Warn => self.value.clone(),
+                    SafetyAction::Comment => comment_out(&self.value),
+                    SafetyAction::Refuse => {
+                        port.log(format!("Not inserting it: {}", self.value));
+                        return Ok(());
+                    }
+                }
+            }
+        };
+
+        // the history keeps the command itself, so replaying it shows the warning again
+        if let Some(origin) = self.origin
+            && let Err(error) = history::record(port, origin, &self.value, &self.config.history)
+        {
+            port.log(format!("Note: could not save to history: {error}"));
+        }
+        port.set_final_prompt(inserted);
 
         Ok(())
     }
 }
+
+/// Every line, a multi-line command would otherwise run its later lines on Enter.
+fn comment_out(command: &str) -> String {
+    command
+        .lines()
+        .map(|line| format!("# {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_out_every_line() {
+        assert_eq!(
+            comment_out("cd /var/lib\nrm -rf /"),
+            "# cd /var/lib\n# rm -rf /"
+        );
+        assert_eq!(comment_out("mkfs.ext4 /dev/sda"), "# mkfs.ext4 /dev/sda");
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    config::Config,
+    history::read_entries,
+    prompt::Prompt,
+    utils::{PortTrait, State},
+};
+use anyhow::Result;
+
+/// Injects a command from the history again, without asking the server.
+pub struct Replay {
+    pub id: u64,
+    pub config: Config,
+}
+
+impl State<Prompt> for Replay {
+    async fn next(self, port: &impl PortTrait) -> Result<Prompt> {
+        let Some(entry) = read_entries(port)?
+            .into_iter()
+            .find(|entry| entry.id == self.id)
+        else {
+            return Err(anyhow::anyhow!(
+                "No history entry with id {}, see `hey --history`",
+                self.id
+            ));
+        };
+
+        Ok(Prompt {
+            value: entry.command,
+            explain: None,
+            config: self.config,
+            origin: None,
+        })
+    }
+}
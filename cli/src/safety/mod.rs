@@ -0,0 +1,904 @@
+use crate::config::SafetyConfig;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Why a command was flagged, `rule` can be listed in `disabled_rules` to silence it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+/// Flags destructive commands before they get injected in the shell.
+pub struct SafetyAnalyzer {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+    disabled_rules: Vec<String>,
+}
+
+impl SafetyAnalyzer {
+    pub fn new(config: &SafetyConfig) -> Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern)
+                        .with_context(|| format!("Invalid safety pattern: {pattern}"))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            allow: compile(&config.allow)?,
+            deny: compile(&config.deny)?,
+            disabled_rules: config.disabled_rules.clone(),
+        })
+    }
+
+    /// Built-in rules are skipped for commands matching an `allow` pattern,
+    /// `deny` patterns are always checked.
+    pub fn analyze(&self, command: &str) -> Vec<Finding> {
+        let mut findings = vec![];
+
+        if !self.allow.iter().any(|pattern| pattern.is_match(command)) {
+            if is_fork_bomb(command) {
+                findings.push(Finding {
+                    rule: "fork-bomb",
+                    message: "defines a function that endlessly spawns itself".to_string(),
+                });
+            }
+            check_script(&parse(command), &mut findings);
+            findings.retain(|finding| !self.disabled_rules.iter().any(|rule| rule == finding.rule));
+            // a substitution in a `sh -c` script is seen both in the argument and in the script
+            let mut unique = vec![];
+            for finding in findings.drain(..) {
+                if !unique.contains(&finding) {
+                    unique.push(finding);
+                }
+            }
+            findings = unique;
+        }
+
+        for pattern in &self.deny {
+            if pattern.is_match(command) {
+                findings.push(Finding {
+                    rule: "deny",
+                    message: format!("matches the deny pattern `{pattern}`"),
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+/// Commands separated by `;`, `&&`, `||`, `&` or new lines.
+#[derive(Debug, Default)]
+struct Script {
+    pipelines: Vec<Pipeline>,
+}
+
+/// Commands connected with `|`.
+#[derive(Debug, Default)]
+struct Pipeline {
+    commands: Vec<SimpleCommand>,
+}
+
+#[derive(Debug, Default)]
+struct SimpleCommand {
+    words: Vec<String>,
+    /// Operator and target, eg: `(">>", "/tmp/log")`.
+    redirects: Vec<(String, String)>,
+    /// `$(...)`, backticks and `<(...)` found in the words or redirects.
+    substitutions: Vec<Script>,
+}
+
+#[derive(Debug)]
+enum Token {
+    Word {
+        text: String,
+        substitutions: Vec<Script>,
+    },
+    Operator(&'static str),
+    Redirect(String),
+}
+
+fn parse(input: &str) -> Script {
+    let mut script = Script::default();
+    let mut pipeline = Pipeline::default();
+    let mut command = SimpleCommand::default();
+    let mut pending_redirect = None;
+
+    fn end_command(pipeline: &mut Pipeline, command: &mut SimpleCommand) {
+        let command = std::mem::take(command);
+        if !command.words.is_empty() || !command.redirects.is_empty() {
+            pipeline.commands.push(command);
+        }
+    }
+    fn end_pipeline(script: &mut Script, pipeline: &mut Pipeline) {
+        let pipeline = std::mem::take(pipeline);
+        if !pipeline.commands.is_empty() {
+            script.pipelines.push(pipeline);
+        }
+    }
+
+    for token in lex(input) {
+        match token {
+            Token::Word {
+                text,
+                substitutions,
+            } => {
+                command.substitutions.extend(substitutions);
+                match pending_redirect.take() {
+                    Some(operator) => command.redirects.push((operator, text)),
+                    None => command.words.push(text),
+                }
+            }
+            Token::Redirect(operator) => pending_redirect = Some(operator),
+            Token::Operator("|") => end_command(&mut pipeline, &mut command),
+            Token::Operator(_) => {
+                end_command(&mut pipeline, &mut command);
+                end_pipeline(&mut script, &mut pipeline);
+            }
+        }
+    }
+
+    end_command(&mut pipeline, &mut command);
+    end_pipeline(&mut script, &mut pipeline);
+    script
+}
+
+fn lex(input: &str) -> Vec<Token> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            ' ' | '\t' => i += 1,
+            '\n' | ';' => {
+                tokens.push(Token::Operator(";"));
+                i += 1;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '|' => {
+                let (operator, len) = match next {
+                    Some('|') => ("||", 2),
+                    Some('&') => ("|", 2),
+                    _ => ("|", 1),
+                };
+                tokens.push(Token::Operator(operator));
+                i += len;
+            }
+            '&' if next == Some('&') => {
+                tokens.push(Token::Operator("&&"));
+                i += 2;
+            }
+            '&' if next == Some('>') => {
+                let len = if chars.get(i + 2) == Some(&'>') { 3 } else { 2 };
+                tokens.push(Token::Redirect(chars[i..i + len].iter().collect()));
+                i += len;
+            }
+            '&' => {
+                tokens.push(Token::Operator("&"));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Operator("("));
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Operator(")"));
+                i += 1;
+            }
+            '<' | '>' if next == Some('(') => {
+                // process substitution, eg: `bash <(curl ...)`
+                let (content, end) = read_balanced(&chars, i + 2);
+                tokens.push(Token::Word {
+                    text: chars[i..end].iter().collect(),
+                    substitutions: vec![parse(&content)],
+                });
+                i = end;
+            }
+            '<' | '>' => {
+                let start = i;
+                while i < chars.len() && matches!(chars[i], '<' | '>' | '&' | '|') {
+                    i += 1;
+                }
+                tokens.push(Token::Redirect(chars[start..i].iter().collect()));
+            }
+            digit if digit.is_ascii_digit() && is_fd_redirect(&chars, i) => {
+                while chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            _ => {
+                let (token, end) = read_word(&chars, i);
+                tokens.push(token);
+                i = end;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Whether the digits at `start` are a file descriptor, eg: the `2` in `2>/dev/null`.
+fn is_fd_redirect(chars: &[char], start: usize) -> bool {
+    let is_word_start = start == 0 || matches!(chars[start - 1], ' ' | '\t' | '\n' | ';' | '|');
+    let end = start
+        + chars[start..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+    is_word_start && matches!(chars.get(end), Some('<' | '>'))
+}
+
+fn read_word(chars: &[char], start: usize) -> (Token, usize) {
+    let mut text = String::new();
+    let mut substitutions = vec![];
+    let mut i = start;
+
+    while i < chars.len() {
+        match chars[i] {
+            ' ' | '\t' | '\n' | ';' | '|' | '&' | '(' | ')' | '<' | '>' => break,
+            '\\' => {
+                if let Some(escaped) = chars.get(i + 1) {
+                    text.push(*escaped);
+                }
+                i += 2;
+            }
+            '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    text.push(chars[i]);
+                    i += 1;
+                }
+                i += 1;
+            }
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        '$' if chars.get(i + 1) == Some(&'(') => {
+                            let (content, end) = read_balanced(chars, i + 2);
+                            substitutions.push(parse(&content));
+                            text.extend(&chars[i..end]);
+                            i = end;
+                        }
+                        '`' => {
+                            let (content, end) = read_backticks(chars, i + 1);
+                            substitutions.push(parse(&content));
+                            text.extend(&chars[i..end]);
+                            i = end;
+                        }
+                        c => {
+                            text.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            '$' if chars.get(i + 1) == Some(&'(') => {
+                let (content, end) = read_balanced(chars, i + 2);
+                substitutions.push(parse(&content));
+                text.extend(&chars[i..end]);
+                i = end;
+            }
+            '`' => {
+                let (content, end) = read_backticks(chars, i + 1);
+                substitutions.push(parse(&content));
+                text.extend(&chars[i..end]);
+                i = end;
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    (
+        Token::Word {
+            text,
+            substitutions,
+        },
+        i.min(chars.len()),
+    )
+}
+
+/// Reads up to the `)` matching an already consumed `(`, returns the content and the index after `)`.
+fn read_balanced(chars: &[char], start: usize) -> (String, usize) {
+    let mut depth = 1;
+    let mut quote = None;
+    let mut i = start;
+
+    while i < chars.len() {
+        match (chars[i], quote) {
+            ('\\', _) => i += 1,
+            (c @ ('\'' | '"'), None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('(', None) => depth += 1,
+            (')', None) => {
+                depth -= 1;
+                if depth == 0 {
+                    return (chars[start..i].iter().collect(), i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    (
+        chars[start.min(chars.len())..].iter().collect(),
+        chars.len(),
+    )
+}
+
+/// Reads up to the closing backtick, returns the content and the index after it.
+fn read_backticks(chars: &[char], start: usize) -> (String, usize) {
+    let end = chars[start.min(chars.len())..]
+        .iter()
+        .position(|c| *c == '`')
+        .map_or(chars.len(), |position| start + position);
+    (
+        chars[start.min(end)..end].iter().collect(),
+        (end + 1).min(chars.len()),
+    )
+}
+
+impl SimpleCommand {
+    /// The program actually run and its arguments, skipping env assignments
+    /// and wrappers like `sudo`, eg: `rm` for `sudo -u root FOO=1 /bin/rm -rf /`.
+    fn program(&self) -> Option<(&str, &[String])> {
+        let mut words = self.words.as_slice();
+        loop {
+            let (first, rest) = words.split_first()?;
+            let name = first.rsplit('/').next().unwrap_or(first);
+
+            let is_assignment = first
+                .split_once('=')
+                .is_some_and(|(key, _)| !key.is_empty() && !key.starts_with('-'));
+            if is_assignment {
+                words = rest;
+                continue;
+            }
+
+            let options_with_value: &[&str] = match name {
+                "sudo" | "doas" => &["-u", "-g", "-C", "-D", "-h", "-p", "-r", "-t", "-U"],
+                "env" => &["-u", "-C", "-S"],
+                "nice" => &["-n"],
+                "xargs" => &["-I", "-L", "-n", "-P", "-d", "-E", "-s", "-a"],
+                "nohup" | "time" | "command" | "exec" | "builtin" | "busybox" => &[],
+                _ => return Some((name, rest)),
+            };
+
+            words = rest;
+            while let Some((option, rest)) = words.split_first() {
+                if !option.starts_with('-') {
+                    break;
+                }
+                words = match (options_with_value.contains(&option.as_str()), rest) {
+                    (true, [_, rest @ ..]) => rest,
+                    _ => rest,
+                };
+            }
+        }
+    }
+}
+
+fn check_script(script: &Script, findings: &mut Vec<Finding>) {
+    for pipeline in &script.pipelines {
+        check_pipeline(pipeline, findings);
+        for command in &pipeline.commands {
+            check_command(command, findings);
+            for substitution in &command.substitutions {
+                check_script(substitution, findings);
+            }
+        }
+    }
+}
+
+const DOWNLOADERS: &[&str] = &[
+    "curl",
+    "wget",
+    "fetch",
+    "http",
+    "https",
+    "aria2c",
+    "irm",
+    "iwr",
+    "Invoke-RestMethod",
+    "Invoke-WebRequest",
+];
+
+const INTERPRETERS: &[&str] = &[
+    "sh",
+    "bash",
+    "zsh",
+    "fish",
+    "dash",
+    "ksh",
+    "csh",
+    "tcsh",
+    "python",
+    "python3",
+    "perl",
+    "ruby",
+    "node",
+    "pwsh",
+    "powershell",
+    "iex",
+    "Invoke-Expression",
+];
+
+/// Interpreters running their `-c` argument as a shell script.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "fish", "dash", "ksh", "csh", "tcsh"];
+
+fn check_pipeline(pipeline: &Pipeline, findings: &mut Vec<Finding>) {
+    let programs = pipeline
+        .commands
+        .iter()
+        .filter_map(|command| command.program().map(|(program, _)| program))
+        .collect::<Vec<_>>();
+
+    let downloader = programs
+        .iter()
+        .position(|program| DOWNLOADERS.contains(program));
+    let interpreter = programs
+        .iter()
+        .rposition(|program| INTERPRETERS.contains(program));
+    if let (Some(downloader), Some(interpreter)) = (downloader, interpreter)
+        && downloader < interpreter
+    {
+        findings.push(Finding {
+            rule: "pipe-to-shell",
+            message: format!(
+                "runs a script downloaded with `{}` through `{}` without reviewing it",
+                programs[downloader], programs[interpreter]
+            ),
+        });
+    }
+}
+
+fn check_command(command: &SimpleCommand, findings: &mut Vec<Finding>) {
+    for (operator, target) in &command.redirects {
+        if operator.contains('>') && is_disk_device(target) {
+            findings.push(Finding {
+                rule: "write-to-device",
+                message: format!("overwrites the disk `{target}`"),
+            });
+        }
+    }
+
+    let Some((program, args)) = command.program() else {
+        return;
+    };
+
+    match program {
+        "rm" => {
+            if args.iter().any(|arg| arg == "--no-preserve-root") {
+                findings.push(Finding {
+                    rule: "rm-recursive-critical",
+                    message: "disables the protection against deleting `/`".to_string(),
+                });
+            } else if has_recursive_flag(args, &['r', 'R']) {
+                for target in operands(args).filter(|target| is_critical_path(target)) {
+                    findings.push(Finding {
+                        rule: "rm-recursive-critical",
+                        message: format!("recursively deletes `{target}`"),
+                    });
+                }
+            }
+        }
+        "chmod" | "chown" | "chgrp" if has_recursive_flag(args, &['R']) => {
+            for target in operands(args).filter(|target| is_critical_path(target)) {
+                findings.push(Finding {
+                    rule: "chmod-recursive-critical",
+                    message: format!("recursively changes permissions of `{target}`"),
+                });
+            }
+        }
+        "dd" => {
+            for target in args.iter().filter_map(|arg| arg.strip_prefix("of=")) {
+                if is_disk_device(target) {
+                    findings.push(Finding {
+                        rule: "write-to-device",
+                        message: format!("overwrites the disk `{target}`"),
+                    });
+                }
+            }
+        }
+        "diskutil" => {
+            let verb = args.first().map(String::as_str).unwrap_or_default();
+            if verb.starts_with("erase") || verb.starts_with("partition") {
+                findings.push(Finding {
+                    rule: "format-disk",
+                    message: format!("erases a disk with `diskutil {verb}`"),
+                });
+            }
+        }
+        program
+            if program.starts_with("mkfs")
+                || ["mke2fs", "mkswap", "newfs", "wipefs"].contains(&program) =>
+        {
+            findings.push(Finding {
+                rule: "format-disk",
+                message: format!("formats a disk with `{program}`"),
+            });
+        }
+        _ => {}
+    }
+
+    if SHELLS.contains(&program)
+        && let Some(script) = shell_script(args)
+    {
+        check_script(&parse(script), findings);
+    }
+
+    if INTERPRETERS.contains(&program) {
+        let runs_download = command.substitutions.iter().any(|substitution| {
+            substitution
+                .pipelines
+                .iter()
+                .flat_map(|pipeline| &pipeline.commands)
+                .filter_map(|command| command.program())
+                .any(|(program, _)| DOWNLOADERS.contains(&program))
+        });
+        if runs_download {
+            findings.push(Finding {
+                rule: "pipe-to-shell",
+                message: format!(
+                    "runs a downloaded script through `{program}` without reviewing it"
+                ),
+            });
+        }
+    }
+}
+
+/// The argument following `-c`, or combined flags such as `-lc`.
+fn shell_script(args: &[String]) -> Option<&str> {
+    let flag = args.iter().take_while(|arg| *arg != "--").position(|arg| {
+        arg.strip_prefix('-')
+            .is_some_and(|short| !short.starts_with('-') && short.contains('c'))
+    })?;
+    args.get(flag + 1).map(String::as_str)
+}
+
+/// Eg: `-r`, `-rf`, `-fR` or `--recursive`.
+fn has_recursive_flag(args: &[String], short_flags: &[char]) -> bool {
+    args.iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| match arg.strip_prefix("--") {
+            Some(long) => long == "recursive",
+            None => arg
+                .strip_prefix('-')
+                .is_some_and(|short| short.chars().any(|c| short_flags.contains(&c))),
+        })
+}
+
+/// Arguments that are not flags.
+fn operands(args: &[String]) -> impl Iterator<Item = &String> {
+    let end_of_flags = args.iter().position(|arg| arg == "--");
+    args.iter().enumerate().filter_map(move |(index, arg)| {
+        let after_end_of_flags = end_of_flags.is_some_and(|end| index > end);
+        (after_end_of_flags || !arg.starts_with('-')).then_some(arg)
+    })
+}
+
+const CRITICAL_DIRECTORIES: &[&str] = &[
+    "/",
+    "~",
+    "$HOME",
+    "${HOME}",
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/home",
+    "/lib",
+    "/lib64",
+    "/opt",
+    "/proc",
+    "/root",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var",
+    "/Applications",
+    "/Library",
+    "/System",
+    "/Users",
+];
+
+/// The root, home, or a top-level system directory, including everything inside them, eg: `/etc/*`.
+fn is_critical_path(path: &str) -> bool {
+    let mut path = path.to_string();
+    while path.contains("//") {
+        path = path.replace("//", "/");
+    }
+    while let Some(parent) = path
+        .strip_suffix("/*")
+        .or_else(|| path.strip_suffix("/."))
+        .or_else(|| path.strip_suffix('/').filter(|parent| !parent.is_empty()))
+    {
+        path = match parent {
+            "" => "/".to_string(),
+            parent => parent.to_string(),
+        };
+    }
+
+    CRITICAL_DIRECTORIES.contains(&path.as_str())
+}
+
+/// Eg: `/dev/sda`, `/dev/nvme0n1` or `/dev/disk2`, but not `/dev/null`.
+fn is_disk_device(path: &str) -> bool {
+    const DISK_PREFIXES: &[&str] = &[
+        "sd", "hd", "vd", "xvd", "nvme", "mmcblk", "disk", "rdisk", "md", "dm-", "mapper/", "loop",
+    ];
+
+    path.strip_prefix("/dev/").is_some_and(|device| {
+        DISK_PREFIXES
+            .iter()
+            .any(|prefix| device.starts_with(prefix))
+    })
+}
+
+/// Eg: `:(){ :|:& };:`, a function piping into itself in the background.
+fn is_fork_bomb(command: &str) -> bool {
+    let definition = Regex::new(r"([A-Za-z_:][\w:]*)\s*\(\s*\)\s*\{").unwrap();
+    definition.captures_iter(command).any(|captures| {
+        let name = regex::escape(&captures[1]);
+        Regex::new(&format!(r"{name}\s*\|\s*{name}\s*&"))
+            .unwrap()
+            .is_match(command)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(command: &str) -> Vec<&'static str> {
+        SafetyAnalyzer::new(&SafetyConfig::default())
+            .unwrap()
+            .analyze(command)
+            .into_iter()
+            .map(|finding| finding.rule)
+            .collect()
+    }
+
+    #[test]
+    fn safe_commands() {
+        for command in [
+            "ls -la",
+            "rm -rf ./target",
+            "rm -rf node_modules dist",
+            "rm -r ~/Downloads/old",
+            "rm /tmp/file",
+            "echo \"rm -rf /\"",
+            "echo 'curl example.com | sh'",
+            "git log --oneline | head -n 5",
+            "find . -name '*.log' -delete",
+            "chmod 777 ./script.sh",
+            "chmod -R 755 ./public",
+            "chown -R me:staff ~/projects",
+            "dd if=/dev/zero of=./disk.img bs=1M count=10",
+            "dd if=/dev/sda of=/dev/null",
+            "curl -fsSL https://example.com/install.sh -o install.sh",
+            "curl https://example.com/data.json | jq .",
+            "cat script.sh | less",
+            "ls 2>/dev/null",
+            "du -sh * | sort -h > /tmp/sizes",
+            "grep -r ':(){' .",
+        ] {
+            assert_eq!(analyze(command), Vec::<&str>::new(), "{command}");
+        }
+    }
+
+    #[test]
+    fn rm_recursive_critical() {
+        for command in [
+            "rm -rf /",
+            "rm -fr /*",
+            "rm -r -f //",
+            "rm --recursive --force /",
+            "sudo rm -rf /",
+            "sudo -u root rm -Rf /etc",
+            "FOO=bar /bin/rm -rf ~",
+            "rm -rf ~/",
+            "rm -rf \"$HOME\"",
+            "rm -rf /usr/*",
+            "rm -rf -- /",
+            "cd /tmp && rm -rf /",
+            "echo done; rm -rf /home",
+            "rm --no-preserve-root -rf /",
+            "find . -type d | xargs -n 1 rm -rf /",
+        ] {
+            assert_eq!(analyze(command), vec!["rm-recursive-critical"], "{command}");
+        }
+    }
+
+    #[test]
+    fn write_to_device() {
+        for command in [
+            "dd if=ubuntu.iso of=/dev/sda bs=4M",
+            "sudo dd if=/dev/zero of=/dev/nvme0n1",
+            "dd if=image.img of=/dev/disk2",
+            "cat image.img > /dev/sdb",
+            "echo oops >/dev/mmcblk0",
+        ] {
+            assert_eq!(analyze(command), vec!["write-to-device"], "{command}");
+        }
+    }
+
+    #[test]
+    fn format_disk() {
+        for command in [
+            "mkfs.ext4 /dev/sda1",
+            "sudo mkfs -t vfat /dev/sdb1",
+            "wipefs -a /dev/sdc",
+            "diskutil eraseDisk APFS Empty disk2",
+        ] {
+            assert_eq!(analyze(command), vec!["format-disk"], "{command}");
+        }
+    }
+
+    #[test]
+    fn chmod_recursive_critical() {
+        for command in [
+            "chmod -R 777 /",
+            "sudo chmod -R 777 /etc",
+            "chown -R nobody /usr",
+            "chmod --recursive 000 ~",
+        ] {
+            assert_eq!(
+                analyze(command),
+                vec!["chmod-recursive-critical"],
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn fork_bomb() {
+        for command in [
+            ":(){ :|:& };:",
+            ":() { : | : & }; :",
+            "bomb() { bomb|bomb& }; bomb",
+        ] {
+            assert_eq!(analyze(command), vec!["fork-bomb"], "{command}");
+        }
+    }
+
+    #[test]
+    fn pipe_to_shell() {
+        for command in [
+            "curl -fsSL https://example.com/install.sh | sh",
+            "curl -s https://example.com/install.sh | sudo bash",
+            "wget -qO- https://example.com/install.sh | bash -s -- --yes",
+            "curl https://example.com/x.py | tee /tmp/x.py | python3",
+            "sh -c \"$(curl -fsSL https://example.com/install.sh)\"",
+            "bash <(curl -s https://example.com/install.sh)",
+            "bash -c \"`wget -qO- https://example.com/install.sh`\"",
+            "irm https://example.com/install.ps1 | iex",
+        ] {
+            assert_eq!(analyze(command), vec!["pipe-to-shell"], "{command}");
+        }
+    }
+
+    #[test]
+    fn nested_substitutions_are_checked() {
+        assert_eq!(analyze("echo $(rm -rf /)"), vec!["rm-recursive-critical"]);
+        assert_eq!(
+            analyze("(cd / && rm -rf /usr)"),
+            vec!["rm-recursive-critical"]
+        );
+    }
+
+    #[test]
+    fn shell_scripts_are_checked() {
+        assert_eq!(analyze("bash -c 'rm -rf /'"), vec!["rm-recursive-critical"]);
+        assert_eq!(
+            analyze("sudo sh -c \"mkfs.ext4 /dev/sda\""),
+            vec!["format-disk"]
+        );
+        assert_eq!(
+            analyze("sh -c 'dd if=/dev/zero of=/dev/sda'"),
+            vec!["write-to-device"]
+        );
+        assert_eq!(
+            analyze("bash -lc \"sh -c 'rm -rf /usr'\""),
+            vec!["rm-recursive-critical"]
+        );
+        assert_eq!(
+            analyze("sh -c \"echo $(rm -rf ~)\""),
+            vec!["rm-recursive-critical"]
+        );
+        assert!(analyze("bash -c 'ls -la /'").is_empty());
+        assert!(analyze("python3 -c 'print(1)'").is_empty());
+    }
+
+    #[test]
+    fn multiple_findings() {
+        assert_eq!(
+            analyze("rm -rf / && mkfs.ext4 /dev/sda1"),
+            vec!["rm-recursive-critical", "format-disk"]
+        );
+    }
+
+    #[test]
+    fn allow_deny_and_disabled_rules() {
+        let config = SafetyConfig {
+            allow: vec![
+                r"^curl -fsSL https://hey-cli\.zak-man\.com/install\.sh \| sh$".to_string(),
+            ],
+            deny: vec![r"\bgit\s+push\b.*--force".to_string()],
+            disabled_rules: vec!["format-disk".to_string()],
+            ..SafetyConfig::default()
+        };
+        let analyzer = SafetyAnalyzer::new(&config).unwrap();
+
+        assert!(
+            analyzer
+                .analyze("curl -fsSL https://hey-cli.zak-man.com/install.sh | sh")
+                .is_empty()
+        );
+        assert_eq!(
+            analyzer.analyze("curl -fsSL https://example.com/install.sh | sh")[0].rule,
+            "pipe-to-shell"
+        );
+        assert_eq!(
+            analyzer.analyze("git push origin main --force")[0].rule,
+            "deny"
+        );
+        assert!(analyzer.analyze("mkfs.ext4 /dev/sda1").is_empty());
+    }
+
+    #[test]
+    fn invalid_pattern_is_reported() {
+        let config = SafetyConfig {
+            deny: vec!["(".to_string()],
+            ..SafetyConfig::default()
+        };
+        let error = SafetyAnalyzer::new(&config).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid safety pattern: (");
+    }
+
+    #[test]
+    fn critical_paths() {
+        for path in [
+            "/", "//", "/*", "/.", "~", "~/", "~/*", "$HOME", "/etc/", "/usr/*",
+        ] {
+            assert!(is_critical_path(path), "{path}");
+        }
+        for path in [
+            "./",
+            ".",
+            "*",
+            "/etc/nginx",
+            "~/Downloads",
+            "/tmp/x",
+            "target",
+        ] {
+            assert!(!is_critical_path(path), "{path}");
+        }
+    }
+}
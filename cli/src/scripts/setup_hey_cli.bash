@@ -0,0 +1,33 @@
+__hey_cli_pending_prompt=""
+
+__hey_cli_insert_prompt() {
+    READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}${__hey_cli_pending_prompt}${READLINE_LINE:$READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#__hey_cli_pending_prompt}))
+    __hey_cli_pending_prompt=""
+}
+
+# the terminal answers the `\e[5n` status report with `\e[0n`, which readline
+# receives as input on the next prompt, triggering the insertion above
+if [[ $- == *i* ]]; then
+    bind -x '"\e[0n": __hey_cli_insert_prompt'
+fi
+
+hey() {
+    local hey_setup_version="0.1.0"
+    local name="bash"
+    # TODO: check if `hey` is in path, otherwise print install instructions
+    local hey_cli=$(type -P hey)
+
+    local hey_output=$("$hey_cli" --shell-name $name --setup-version $hey_setup_version "$@")
+    local stdout=$("$hey_cli" --get-stdout "$hey_output")
+    local prompt=$("$hey_cli" --get-prompt "$hey_output")
+
+    # TODO: skip echoing stdout if it's empty
+    echo "$stdout"
+    if [[ -n "$prompt" ]]; then
+        # also keep it in history, so it's one arrow-up away if injection fails
+        history -s "$prompt"
+        __hey_cli_pending_prompt="$prompt"
+        printf '\e[5n'
+    fi
+}
@@ -0,0 +1,20 @@
+function hey {
+    $hey_setup_version = "0.1.0"
+    $name = "power_shell"
+    # TODO: check if `hey` is in path, otherwise print install instructions
+    $hey_cli = (Get-Command hey -CommandType Application | Select-Object -First 1).Source
+
+    $hey_output = (& $hey_cli --shell-name $name --setup-version $hey_setup_version @args) -join "`n"
+    $stdout = (& $hey_cli --get-stdout $hey_output) -join "`n"
+    $prompt = (& $hey_cli --get-prompt $hey_output) -join "`n"
+
+    # TODO: skip echoing stdout if it's empty
+    Write-Host $stdout
+    if ($prompt) {
+        # PSReadLine resets its buffer before reading the next line, so the
+        # insertion is deferred until the engine is idle on the new prompt
+        $null = Register-EngineEvent -SourceIdentifier PowerShell.OnIdle -MaxTriggerCount 1 -MessageData $prompt -Action {
+            [Microsoft.PowerShell.PSConsoleReadLine]::Insert($Event.MessageData)
+        }
+    }
+}
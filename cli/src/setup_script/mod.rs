@@ -1,52 +1,33 @@
-use crate::utils::{PortTrait, ShellName, State};
+use crate::utils::{FilePort, PortTrait, ShellName, State};
 use anyhow::Result;
+use std::path::Path;
 use strum::IntoEnumIterator;
 
-pub struct SetupScript;
+pub struct SetupScript {
+    /// The shell running an outdated setup script, `None` when not set up yet.
+    pub shell: Option<ShellName>,
+}
 
 impl State<()> for SetupScript {
     async fn next(self, port: &impl PortTrait) -> Result<()> {
-        for shell_name in ShellName::iter() {
+        let home_dir = dirs::home_dir().ok_or(anyhow::anyhow!("Could not find home directory"))?;
+
+        let shell_names = shells_to_set_up(port, &home_dir, self.shell, &|binary| {
+            port.is_on_path(binary)
+        })?;
+        if shell_names.is_empty() {
+            let supported = ShellName::iter()
+                .map(|shell_name| shell_name.to_string())
+                .collect::<Vec<_>>();
+            anyhow::bail!(
+                "Could not find a supported shell to set up, supported shells are: {}",
+                supported.join(", ")
+            );
+        }
+
+        for shell_name in shell_names {
             port.log(format!("Installing setup script for shell: {shell_name}"));
-            match shell_name {
-                ShellName::Fish => {
-                    let setup = shell_name.setup_script_content();
-                    let fish_setup_relative_path = ".config/fish/functions/setup_hey_cli.fish";
-                    let home_dir =
-                        dirs::home_dir().ok_or(anyhow::anyhow!("Could not find home directory"))?;
-                    let fish_setup_path = home_dir.join(fish_setup_relative_path);
-
-                    port.overwrite_file(&fish_setup_path, setup)?;
-
-                    let fish_config_path = home_dir.join(".config/fish/config.fish");
-                    let config_source_line = format!("source ~/{}", fish_setup_relative_path);
-
-                    port.remove_matches_from_file_content(&fish_config_path, &config_source_line)?;
-                    port.append_to_file(&fish_config_path, &config_source_line)?;
-                }
-                ShellName::Zsh => {
-                    let setup = shell_name.setup_script_content();
-                    let zsh_setup_relative_path = ".config/zsh/functions/setup_hey_cli.zsh";
-                    let home_dir =
-                        dirs::home_dir().ok_or(anyhow::anyhow!("Could not find home directory"))?;
-                    let zsh_setup_path = home_dir.join(zsh_setup_relative_path);
-
-                    port.overwrite_file(&zsh_setup_path, setup)?;
-
-                    let zshrc_path = home_dir.join(".zshrc");
-                    let fpath_line = "fpath=(~/.config/zsh/functions $fpath)";
-                    let source_line = format!("source ~/{}", zsh_setup_relative_path);
-                    let autoload_line = "autoload -Uz hey";
-
-                    for line in [fpath_line, &source_line, autoload_line] {
-                        port.remove_matches_from_file_content(&zshrc_path, line)?;
-                        port.append_to_file(&zshrc_path, line)?;
-                    }
-                }
-                _ => {
-                    port.log(format!("{shell_name} shell is not yet supported"));
-                }
-            };
+            shell_name.integration().install(port, &home_dir)?;
         }
 
         port.log("Setup script installed successfully");
@@ -54,3 +35,74 @@ impl State<()> for SetupScript {
         Ok(())
     }
 }
+
+/// The current shell and the ones found, installing for every supported shell would leave
+/// config files behind for shells never installed.
+fn shells_to_set_up(
+    files: &dyn FilePort,
+    home_dir: &Path,
+    current: Option<ShellName>,
+    is_on_path: &dyn Fn(&str) -> bool,
+) -> Result<Vec<ShellName>> {
+    let mut shell_names = vec![];
+    for shell_name in ShellName::iter() {
+        if Some(shell_name) == current
+            || shell_name
+                .integration()
+                .is_present(files, home_dir, is_on_path)?
+        {
+            shell_names.push(shell_name);
+        }
+    }
+    Ok(shell_names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryFiles;
+
+    #[test]
+    fn sets_up_present_shells_only() {
+        let home_dir = Path::new("/home/hey");
+        let files = MemoryFiles::default();
+        let bash = ShellName::Bash.integration();
+        files
+            .overwrite_file(&bash.config_path(home_dir), "# user config\n")
+            .unwrap();
+        let zsh = ShellName::Zsh.integration();
+        files
+            .overwrite_file(&zsh.script_path(home_dir), "# an older setup script\n")
+            .unwrap();
+
+        let shell_names = shells_to_set_up(&files, home_dir, Some(ShellName::Xonsh), &|binary| {
+            binary == "fish"
+        })
+        .unwrap();
+        assert_eq!(
+            shell_names,
+            vec![
+                ShellName::Fish,
+                ShellName::Bash,
+                ShellName::Zsh,
+                ShellName::Xonsh
+            ]
+        );
+
+        for shell_name in &shell_names {
+            shell_name.integration().install(&files, home_dir).unwrap();
+        }
+        for shell_name in ShellName::iter().filter(|name| !shell_names.contains(name)) {
+            let integration = shell_name.integration();
+            assert!(!integration.is_installed(&files, home_dir).unwrap());
+            let config = files.read_file(&integration.config_path(home_dir)).unwrap();
+            assert_eq!(config, None, "{shell_name}");
+        }
+
+        assert!(
+            shells_to_set_up(&MemoryFiles::default(), home_dir, None, &|_| false)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use super::{ShellIntegration, value_after};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Bash;
+
+impl ShellIntegration for Bash {
+    fn name(&self) -> ShellName {
+        ShellName::Bash
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.bash")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/bash/setup_hey_cli.bash")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".bashrc")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["bash"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        vec!["source ~/.config/bash/setup_hey_cli.bash"]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "local hey_setup_version=")
+    }
+}
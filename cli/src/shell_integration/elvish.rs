@@ -0,0 +1,39 @@
+use super::{ShellIntegration, value_after};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Elvish;
+
+impl ShellIntegration for Elvish {
+    fn name(&self) -> ShellName {
+        ShellName::Elvish
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.elv")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        // modules under `lib` can be imported with `use`
+        home_dir.join(".config/elvish/lib/setup_hey_cli.elv")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/elvish/rc.elv")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["elvish"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        vec![
+            "use setup_hey_cli",
+            "fn hey {|@ask| setup_hey_cli:hey $@ask }",
+        ]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "var hey_setup_version = ")
+    }
+}
@@ -0,0 +1,35 @@
+use super::{ShellIntegration, value_after};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Fish;
+
+impl ShellIntegration for Fish {
+    fn name(&self) -> ShellName {
+        ShellName::Fish
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.fish")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/fish/functions/setup_hey_cli.fish")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/fish/config.fish")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["fish"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        vec!["source ~/.config/fish/functions/setup_hey_cli.fish"]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "set hey_setup_version ")
+    }
+}
@@ -0,0 +1,211 @@
+mod bash;
+mod elvish;
+mod fish;
+mod nushell;
+mod power_shell;
+mod xonsh;
+mod zsh;
+
+use crate::utils::{FilePort, ShellName};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Everything needed to install, detect and uninstall hey-cli for a single shell.
+pub trait ShellIntegration {
+    fn name(&self) -> ShellName;
+    fn script_content(&self) -> &'static str;
+    /// Where the setup script gets written to.
+    fn script_path(&self, home_dir: &Path) -> PathBuf;
+    /// The shell's own config file, where the setup script gets loaded from.
+    fn config_path(&self, home_dir: &Path) -> PathBuf;
+    /// Executables of the shell, any of them on `PATH` means it's installed.
+    fn binaries(&self) -> &'static [&'static str];
+    /// Lines appended to the config file to load the setup script.
+    fn rc_lines(&self) -> Vec<&'static str>;
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str>;
+
+    fn setup_version(&self) -> &'static str {
+        self.extract_version(self.script_content())
+            .unwrap_or_else(|| panic!("Could not find setup version for {:}", self.name()))
+    }
+
+    fn install(&self, files: &dyn FilePort, home_dir: &Path) -> Result<()> {
+        files.overwrite_file(&self.script_path(home_dir), self.script_content())?;
+
+        let config_path = self.config_path(home_dir);
+        for line in self.rc_lines() {
+            files.remove_matches_from_file_content(&config_path, line)?;
+            files.append_to_file(&config_path, line)?;
+        }
+
+        Ok(())
+    }
+
+    fn uninstall(&self, files: &dyn FilePort, home_dir: &Path) -> Result<()> {
+        let config_path = self.config_path(home_dir);
+        for line in self.rc_lines() {
+            files.remove_matches_from_file_content(&config_path, line)?;
+        }
+
+        files.remove_file(&self.script_path(home_dir))
+    }
+
+    /// Whether the shell looks installed: on `PATH`, configured, or already set up.
+    fn is_present(
+        &self,
+        files: &dyn FilePort,
+        home_dir: &Path,
+        is_on_path: &dyn Fn(&str) -> bool,
+    ) -> Result<bool> {
+        Ok(self.binaries().iter().any(|binary| is_on_path(binary))
+            || files.read_file(&self.config_path(home_dir))?.is_some()
+            || self.is_installed(files, home_dir)?)
+    }
+
+    /// Whether any trace of the integration is left, either the script or one of the rc lines.
+    fn is_installed(&self, files: &dyn FilePort, home_dir: &Path) -> Result<bool> {
+        if files.read_file(&self.script_path(home_dir))?.is_some() {
+            return Ok(true);
+        }
+
+        let config = files
+            .read_file(&self.config_path(home_dir))?
+            .unwrap_or_default();
+        Ok(self.rc_lines().iter().any(|line| config.contains(line)))
+    }
+}
+
+impl ShellName {
+    pub fn integration(&self) -> &'static dyn ShellIntegration {
+        match self {
+            ShellName::Fish => &fish::Fish,
+            ShellName::Bash => &bash::Bash,
+            ShellName::Zsh => &zsh::Zsh,
+            ShellName::PowerShell => &power_shell::PowerShell,
+            ShellName::Nushell => &nushell::Nushell,
+            ShellName::Elvish => &elvish::Elvish,
+            ShellName::Xonsh => &xonsh::Xonsh,
+        }
+    }
+}
+
+/// Finds the line containing `marker` and returns what follows it, without quotes.
+fn value_after<'a>(script: &'a str, marker: &str) -> Option<&'a str> {
+    script
+        .lines()
+        .find_map(|line| line.split_once(marker))
+        .map(|(_, value)| value.trim().trim_matches(['"', '\'']))
+}
+
+/// `$XDG_CONFIG_HOME` when set, `~/.config` otherwise.
+fn xdg_config_dir(home_dir: &Path) -> PathBuf {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => home_dir.join(".config"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryFiles;
+    use strum::IntoEnumIterator;
+
+    fn assert_integration(shell_name: ShellName) {
+        let integration = shell_name.integration();
+        let home_dir = Path::new("/home/hey");
+        let files = MemoryFiles::default();
+        let user_config = "# user config\n";
+        files
+            .overwrite_file(&integration.config_path(home_dir), user_config)
+            .unwrap();
+
+        assert_eq!(integration.name().to_string(), shell_name.to_string());
+        assert_eq!(integration.setup_version(), "0.1.0");
+        assert!(!integration.is_installed(&files, home_dir).unwrap());
+
+        // installing twice must not duplicate the rc lines
+        integration.install(&files, home_dir).unwrap();
+        integration.install(&files, home_dir).unwrap();
+        assert!(integration.is_installed(&files, home_dir).unwrap());
+
+        let script = files.read_file(&integration.script_path(home_dir)).unwrap();
+        assert_eq!(script.as_deref(), Some(integration.script_content()));
+
+        let config = files
+            .read_file(&integration.config_path(home_dir))
+            .unwrap()
+            .unwrap();
+        assert!(config.starts_with(user_config));
+        for line in integration.rc_lines() {
+            assert_eq!(config.matches(line).count(), 1, "{shell_name}: {line}");
+        }
+
+        integration.uninstall(&files, home_dir).unwrap();
+        assert!(!integration.is_installed(&files, home_dir).unwrap());
+        let config = files
+            .read_file(&integration.config_path(home_dir))
+            .unwrap()
+            .unwrap();
+        assert!(config.starts_with(user_config));
+        assert_eq!(config.trim(), user_config.trim());
+    }
+
+    #[test]
+    fn fish() {
+        assert_integration(ShellName::Fish);
+    }
+
+    #[test]
+    fn bash() {
+        assert_integration(ShellName::Bash);
+    }
+
+    #[test]
+    fn zsh() {
+        assert_integration(ShellName::Zsh);
+    }
+
+    #[test]
+    fn power_shell() {
+        assert_integration(ShellName::PowerShell);
+    }
+
+    #[test]
+    fn nushell() {
+        assert_integration(ShellName::Nushell);
+    }
+
+    #[test]
+    fn elvish() {
+        assert_integration(ShellName::Elvish);
+    }
+
+    #[test]
+    fn xonsh() {
+        assert_integration(ShellName::Xonsh);
+    }
+
+    #[test]
+    fn script_paths_dont_collide() {
+        let home_dir = Path::new("/home/hey");
+        let mut script_paths = ShellName::iter()
+            .map(|shell_name| shell_name.integration().script_path(home_dir))
+            .collect::<Vec<_>>();
+        let count = script_paths.len();
+        script_paths.sort();
+        script_paths.dedup();
+        assert_eq!(script_paths.len(), count);
+    }
+
+    #[test]
+    fn scripts_report_their_shell_name() {
+        for shell_name in ShellName::iter() {
+            let integration = shell_name.integration();
+            let name = value_after(integration.script_content(), "name = ")
+                .or_else(|| value_after(integration.script_content(), "name="))
+                .or_else(|| value_after(integration.script_content(), "set name "));
+            assert_eq!(name, Some(shell_name.to_string().as_str()));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use super::{ShellIntegration, value_after};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Nushell;
+
+impl Nushell {
+    /// Directory holding nushell's `config.nu`, eg:
+    /// `~/.config/nushell` on Linux, `~/Library/Application Support/nushell` on macOS.
+    fn config_dir(home_dir: &Path) -> PathBuf {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+            _ => dirs::config_dir().unwrap_or_else(|| home_dir.join(".config")),
+        }
+        .join("nushell")
+    }
+}
+
+impl ShellIntegration for Nushell {
+    fn name(&self) -> ShellName {
+        ShellName::Nushell
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.nu")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        Self::config_dir(home_dir).join("setup_hey_cli.nu")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        Self::config_dir(home_dir).join("config.nu")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["nu"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        vec![r#"source ($nu.default-config-dir | path join "setup_hey_cli.nu")"#]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "let hey_setup_version = ")
+    }
+}
@@ -0,0 +1,50 @@
+use super::{ShellIntegration, value_after, xdg_config_dir};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct PowerShell;
+
+impl PowerShell {
+    /// Directory holding the PowerShell (`pwsh`) profile, eg:
+    /// `~/Documents/PowerShell` on Windows, `~/.config/powershell` elsewhere.
+    fn profile_dir(home_dir: &Path) -> PathBuf {
+        if cfg!(windows) {
+            return dirs::document_dir()
+                .unwrap_or_else(|| home_dir.join("Documents"))
+                .join("PowerShell");
+        }
+
+        xdg_config_dir(home_dir).join("powershell")
+    }
+}
+
+impl ShellIntegration for PowerShell {
+    fn name(&self) -> ShellName {
+        ShellName::PowerShell
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.ps1")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        Self::profile_dir(home_dir).join("setup_hey_cli.ps1")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        Self::profile_dir(home_dir).join("Microsoft.PowerShell_profile.ps1")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["pwsh", "powershell"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        // `$PSScriptRoot` resolves to the profile directory on every platform
+        vec![r#". "$PSScriptRoot/setup_hey_cli.ps1""#]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "$hey_setup_version = ")
+    }
+}
@@ -0,0 +1,36 @@
+use super::{ShellIntegration, value_after, xdg_config_dir};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Xonsh;
+
+impl ShellIntegration for Xonsh {
+    fn name(&self) -> ShellName {
+        ShellName::Xonsh
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.xsh")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        xdg_config_dir(home_dir).join("xonsh/setup_hey_cli.xsh")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".xonshrc")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["xonsh"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        // `$XONSH_CONFIG_DIR` follows `$XDG_CONFIG_HOME` as well
+        vec![r#"source @($XONSH_CONFIG_DIR + "/setup_hey_cli.xsh")"#]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "hey_setup_version = ")
+    }
+}
@@ -0,0 +1,39 @@
+use super::{ShellIntegration, value_after};
+use crate::utils::ShellName;
+use std::path::{Path, PathBuf};
+
+pub struct Zsh;
+
+impl ShellIntegration for Zsh {
+    fn name(&self) -> ShellName {
+        ShellName::Zsh
+    }
+
+    fn script_content(&self) -> &'static str {
+        include_str!("../scripts/setup_hey_cli.zsh")
+    }
+
+    fn script_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/zsh/functions/setup_hey_cli.zsh")
+    }
+
+    fn config_path(&self, home_dir: &Path) -> PathBuf {
+        home_dir.join(".zshrc")
+    }
+
+    fn binaries(&self) -> &'static [&'static str] {
+        &["zsh"]
+    }
+
+    fn rc_lines(&self) -> Vec<&'static str> {
+        vec![
+            "fpath=(~/.config/zsh/functions $fpath)",
+            "source ~/.config/zsh/functions/setup_hey_cli.zsh",
+            "autoload -Uz hey",
+        ]
+    }
+
+    fn extract_version<'a>(&self, script: &'a str) -> Option<&'a str> {
+        value_after(script, "local hey_setup_version=")
+    }
+}
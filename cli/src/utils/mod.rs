@@ -1,5 +1,9 @@
+use crate::config::{DirectConfig, ServerConfig};
 use anyhow::Result;
-use hey_cli_common::{GetCliPromptRequestBody, GetCliPromptResponse};
+use hey_cli_common::{
+    CliPrompt, ExplainCommandRequestBody, ExplainCommandResponse, GetCliPromptRequestBody,
+    GetCliPromptResponse,
+};
 use nest_struct::nest_struct;
 use std::{path::Path, sync::Mutex};
 use strum_macros::{Display, EnumIter, EnumString};
@@ -12,56 +16,23 @@ pub trait State<N> {
 pub struct Shell {
     pub setup_version: String,
     pub name: nest! {
-        #[derive(EnumString, EnumIter, Display, Debug)]
+        #[derive(EnumString, EnumIter, Display, Debug, Clone, Copy, PartialEq, Eq)]
         #[strum(serialize_all = "snake_case")]
         pub enum ShellName {
             Fish,
             Bash,
             Zsh,
             PowerShell,
+            Nushell,
+            Elvish,
+            Xonsh,
         }
     },
 }
 
 impl Shell {
     pub fn expected_setup_version(&self) -> &str {
-        match self.name {
-            ShellName::Fish => self
-                .name
-                .setup_script_content()
-                .lines()
-                .find_map(|line| {
-                    if line.contains("set hey_setup_version") {
-                        Some(line.split_whitespace().last().unwrap())
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or_else(|| panic!("Could not find setup version for {:}", self.name)),
-            ShellName::Zsh => self
-                .name
-                .setup_script_content()
-                .lines()
-                .find_map(|line| {
-                    if line.contains("local hey_setup_version=") {
-                        Some(line.split('"').nth(1).unwrap())
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or_else(|| panic!("Could not find setup version for {:}", self.name)),
-            _ => todo!("implement expected_setup_version for {:}", self.name),
-        }
-    }
-}
-
-impl ShellName {
-    pub fn setup_script_content(&self) -> &str {
-        match self {
-            ShellName::Fish => include_str!("../scripts/setup_hey_cli.fish"),
-            ShellName::Zsh => include_str!("../scripts/setup_hey_cli.zsh"),
-            _ => todo!("implement setup_script_content for {:}", self),
-        }
+        self.name.integration().setup_version()
     }
 }
 
@@ -80,42 +51,138 @@ impl Port {
     }
 }
 
-pub trait PortTrait {
-    fn log(&self, log: impl Into<String>);
-    fn set_final_prompt(&self, prompt: String);
-    fn to_stdout_format(&self) -> impl Into<String>;
+/// File system side effects, kept object safe so shell integrations can take them as `&dyn FilePort`.
+pub trait FilePort {
+    fn read_file(&self, path: &Path) -> Result<Option<String>>;
+    /// The last `max_bytes` of the file at most, starting at a whole line.
+    fn read_file_tail(&self, path: &Path, max_bytes: u64) -> Result<Option<String>>;
     fn overwrite_file(&self, path: &Path, content: &str) -> Result<()>;
     fn remove_matches_from_file_content(&self, path: &Path, pattern: &str) -> Result<()>;
     fn append_to_file(&self, path: &Path, content: &str) -> Result<()>;
+    fn remove_file(&self, path: &Path) -> Result<()>;
+}
+
+pub trait PortTrait: FilePort {
+    fn log(&self, log: impl Into<String>);
+    fn set_final_prompt(&self, prompt: String);
+    fn to_stdout_format(&self) -> impl Into<String>;
+    /// Whether an executable named `binary` is on `PATH`.
+    fn is_on_path(&self, binary: &str) -> bool;
+    /// Index of the candidate picked by the user, `None` when cancelled.
+    fn pick_candidate(&self, candidates: &[CliPrompt]) -> Result<Option<usize>>;
     async fn ask_server_for_prompt(
         &self,
         query: GetCliPromptRequestBody,
+        server: &ServerConfig,
+    ) -> Result<GetCliPromptResponse>;
+    async fn ask_model_for_prompt(
+        &self,
+        query: GetCliPromptRequestBody,
+        direct: &DirectConfig,
     ) -> Result<GetCliPromptResponse>;
+    async fn ask_server_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        server: &ServerConfig,
+    ) -> Result<ExplainCommandResponse>;
+    async fn ask_model_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        direct: &DirectConfig,
+    ) -> Result<ExplainCommandResponse>;
 }
 
-// TODO: on non-test env, log directly to stdout, both logs and final_prompt
-impl PortTrait for Mutex<Port> {
-    fn log(&self, log: impl Into<String>) {
-        let log = log.into();
-        let mut port = self.lock().unwrap();
-        port.logs.push(log);
+/// Files kept in memory, for tests of code taking a `&dyn FilePort`.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryFiles(
+    pub std::cell::RefCell<std::collections::HashMap<std::path::PathBuf, String>>,
+);
+
+#[cfg(test)]
+impl FilePort for MemoryFiles {
+    fn read_file(&self, path: &Path) -> Result<Option<String>> {
+        Ok(self.0.borrow().get(path).cloned())
+    }
+    fn read_file_tail(&self, path: &Path, max_bytes: u64) -> Result<Option<String>> {
+        let files = self.0.borrow();
+        let Some(content) = files.get(path) else {
+            return Ok(None);
+        };
+        let start = content.len().saturating_sub(max_bytes as usize);
+        Ok(Some(whole_lines(
+            content.as_bytes()[start..].to_vec(),
+            start > 0,
+        )))
+    }
+    fn overwrite_file(&self, path: &Path, content: &str) -> Result<()> {
+        self.0
+            .borrow_mut()
+            .insert(path.to_path_buf(), content.to_string());
+        Ok(())
+    }
+    fn remove_matches_from_file_content(&self, path: &Path, pattern: &str) -> Result<()> {
+        let mut files = self.0.borrow_mut();
+        let content = files.entry(path.to_path_buf()).or_default();
+        *content = content.replace(pattern, "");
+        Ok(())
+    }
+    fn append_to_file(&self, path: &Path, content: &str) -> Result<()> {
+        let mut files = self.0.borrow_mut();
+        let original = files.entry(path.to_path_buf()).or_default();
+        if !original.is_empty() && !original.ends_with('\n') {
+            original.push('\n');
+        }
+        original.push_str(content);
+        Ok(())
     }
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.0.borrow_mut().remove(path);
+        Ok(())
+    }
+}
 
-    // TODO: try to enforce this to be called only once using Rust's type system
-    fn set_final_prompt(&self, prompt: String) {
-        let mut port = self.lock().unwrap();
-        port.final_prompt = Some(prompt);
+/// `content` as text, without its first line when it may have been cut, eg: the tail of a file.
+fn whole_lines(mut content: Vec<u8>, is_cut: bool) -> String {
+    if is_cut {
+        let first_line_end = content.iter().position(|&byte| byte == b'\n');
+        content.drain(..first_line_end.map_or(content.len(), |end| end + 1));
     }
+    String::from_utf8_lossy(&content).into_owned()
+}
 
-    fn to_stdout_format(&self) -> impl Into<String> {
-        let port = self.lock().unwrap();
-        let logs = port.logs.join("\n");
-        let final_prompt = match port.final_prompt.as_ref() {
-            Some(prompt) => format!("\nhey-cli-prompt-start\n{}", prompt),
-            None => "".to_string(),
-        };
+impl FilePort for Mutex<Port> {
+    #[cfg(test)]
+    fn read_file(&self, _: &Path) -> Result<Option<String>> {
+        Ok(None)
+    }
+    #[cfg(not(test))]
+    fn read_file(&self, path: &Path) -> Result<Option<String>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 
-        format!("{logs}{final_prompt}")
+    #[cfg(test)]
+    fn read_file_tail(&self, _: &Path, _: u64) -> Result<Option<String>> {
+        Ok(None)
+    }
+    #[cfg(not(test))]
+    fn read_file_tail(&self, path: &Path, max_bytes: u64) -> Result<Option<String>> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let start = file.metadata()?.len().saturating_sub(max_bytes);
+        file.seek(SeekFrom::Start(start))?;
+        let mut content = vec![];
+        file.read_to_end(&mut content)?;
+        Ok(Some(whole_lines(content, start > 0)))
     }
 
     #[cfg(test)]
@@ -175,10 +242,26 @@ impl PortTrait for Mutex<Port> {
             std::fs::create_dir_all(parent)?;
         }
 
-        let original_content = std::fs::read_to_string(path)?;
-        let ends_with_newline = original_content.ends_with('\n');
+        // only the last byte is read, appended files like the history can be big
+        let ends_with_newline = match std::fs::File::open(path) {
+            Ok(mut file) => {
+                use std::io::{Read, Seek, SeekFrom};
 
-        let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+                let mut last = [b'\n'];
+                if file.metadata()?.len() > 0 {
+                    file.seek(SeekFrom::End(-1))?;
+                    file.read_exact(&mut last)?;
+                }
+                last[0] == b'\n'
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
 
         if !ends_with_newline {
             file.write_all(b"\n")?;
@@ -188,58 +271,292 @@ impl PortTrait for Mutex<Port> {
         Ok(())
     }
 
+    #[cfg(test)]
+    fn remove_file(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+    #[cfg(not(test))]
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// TODO: on non-test env, log directly to stdout, both logs and final_prompt
+impl PortTrait for Mutex<Port> {
+    fn log(&self, log: impl Into<String>) {
+        let log = log.into();
+        let mut port = self.lock().unwrap();
+        port.logs.push(log);
+    }
+
+    // TODO: try to enforce this to be called only once using Rust's type system
+    fn set_final_prompt(&self, prompt: String) {
+        let mut port = self.lock().unwrap();
+        port.final_prompt = Some(prompt);
+    }
+
+    fn to_stdout_format(&self) -> impl Into<String> {
+        let port = self.lock().unwrap();
+        let logs = port.logs.join("\n");
+        let final_prompt = match port.final_prompt.as_ref() {
+            Some(prompt) => format!("\nhey-cli-prompt-start\n{}", prompt),
+            None => "".to_string(),
+        };
+
+        format!("{logs}{final_prompt}")
+    }
+
+    #[cfg(test)]
+    fn is_on_path(&self, _: &str) -> bool {
+        false
+    }
+    #[cfg(not(test))]
+    fn is_on_path(&self, binary: &str) -> bool {
+        let paths = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default();
+        crate::generate_context::tools::find_in_path(&paths, binary).is_some()
+    }
+
+    #[cfg(test)]
+    fn pick_candidate(&self, _: &[CliPrompt]) -> Result<Option<usize>> {
+        Ok(Some(0))
+    }
+    #[cfg(not(test))]
+    fn pick_candidate(&self, candidates: &[CliPrompt]) -> Result<Option<usize>> {
+        use std::io::IsTerminal;
+
+        // eg: piped or running in CI, take the best candidate
+        if !std::io::stderr().is_terminal() {
+            return Ok(Some(0));
+        }
+
+        crate::pick_prompt::run_picker(candidates)
+    }
+
     #[cfg(test)]
     async fn ask_server_for_prompt(
         &self,
         query: GetCliPromptRequestBody,
+        _: &ServerConfig,
     ) -> Result<GetCliPromptResponse> {
-        use hey_cli_common::CliPrompt;
+        let mut candidates = (1..=query.candidate_count()).map(|rank| CliPrompt {
+            value: match rank {
+                1 => format!("echo \"{}\"", query.ask),
+                _ => format!("echo \"{} #{rank}\"", query.ask),
+            },
+            label: None,
+            explain: query
+                .explain
+                .then(|| format!("echo: prints \"{}\"", query.ask)),
+        });
 
         Ok(GetCliPromptResponse {
-            prompt: CliPrompt {
-                value: format!("echo \"{}\"", query.ask),
-            },
+            prompt: candidates.next().unwrap(),
+            alternatives: candidates.collect(),
+            template_version: None,
         })
     }
     #[cfg(not(test))]
     async fn ask_server_for_prompt(
         &self,
         query: GetCliPromptRequestBody,
+        server: &ServerConfig,
+    ) -> Result<GetCliPromptResponse> {
+        post_to_server(server, "/cli-prompt", &query).await
+    }
+
+    #[cfg(test)]
+    async fn ask_model_for_prompt(
+        &self,
+        query: GetCliPromptRequestBody,
+        _: &DirectConfig,
     ) -> Result<GetCliPromptResponse> {
-        #[cfg(not(debug_assertions))]
-        let server_url = "https://hey-cli.zak-man.com";
-        #[cfg(debug_assertions)]
-        let server_url = "http://0.0.0.0:3000";
-
-        #[cfg(debug_assertions)]
-        {
-            // wait on /health and retry every 1 seconds
-            let url = format!("{server_url}/health");
-            loop {
-                let resp = reqwest::get(&url).await;
-                match resp {
-                    Ok(_) => {
-                        tracing::info!("Server is up.");
-                        break;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Server is not up yet: {e}");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                    }
+        self.ask_server_for_prompt(query, &ServerConfig::default())
+            .await
+    }
+    #[cfg(not(test))]
+    async fn ask_model_for_prompt(
+        &self,
+        query: GetCliPromptRequestBody,
+        direct: &DirectConfig,
+    ) -> Result<GetCliPromptResponse> {
+        let completion = chat_completion(direct, query.to_messages()).await?;
+        let resp = query
+            .parse_completion(&completion)
+            .ok_or_else(empty_model_response)?;
+
+        Ok(resp)
+    }
+
+    #[cfg(test)]
+    async fn ask_server_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        _: &ServerConfig,
+    ) -> Result<ExplainCommandResponse> {
+        use hey_cli_common::CommandPart;
+
+        Ok(ExplainCommandResponse {
+            summary: format!("explains \"{}\"", query.command),
+            parts: query
+                .command
+                .split_whitespace()
+                .map(|token| CommandPart {
+                    token: token.to_string(),
+                    explain: format!("explains {token}"),
+                })
+                .collect(),
+        })
+    }
+    #[cfg(not(test))]
+    async fn ask_server_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        server: &ServerConfig,
+    ) -> Result<ExplainCommandResponse> {
+        post_to_server(server, "/explain-command", &query).await
+    }
+
+    #[cfg(test)]
+    async fn ask_model_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        _: &DirectConfig,
+    ) -> Result<ExplainCommandResponse> {
+        self.ask_server_for_explanation(query, &ServerConfig::default())
+            .await
+    }
+    #[cfg(not(test))]
+    async fn ask_model_for_explanation(
+        &self,
+        query: ExplainCommandRequestBody,
+        direct: &DirectConfig,
+    ) -> Result<ExplainCommandResponse> {
+        let completion = chat_completion(direct, query.to_messages()).await?;
+        let resp = query
+            .parse_completion(&completion)
+            .ok_or_else(empty_model_response)?;
+
+        Ok(resp)
+    }
+}
+
+/// POSTs `body` as JSON to hey-cli-server, turning non-successful responses into `ApiError`s.
+#[cfg(not(test))]
+async fn post_to_server<R: serde::de::DeserializeOwned>(
+    server: &ServerConfig,
+    path: &str,
+    body: &impl serde::Serialize,
+) -> Result<R> {
+    use hey_cli_common::{ApiError, ApiErrorKind};
+
+    // only the local dev server is waited for, a configured one failing is reported right away
+    #[cfg(debug_assertions)]
+    if server.url == crate::config::DEFAULT_SERVER_URL {
+        // wait on /health and retry every 1 seconds
+        let url = format!("{}/health", server.url);
+        loop {
+            let resp = reqwest::get(&url).await;
+            match resp {
+                Ok(_) => {
+                    tracing::info!("Server is up.");
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("Server is not up yet: {e}");
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
                 }
             }
         }
+    }
 
-        let url = format!("{server_url}/cli-prompt");
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(url)
-            .json(&query)
-            .send()
-            .await?
-            .json::<GetCliPromptResponse>()
-            .await?;
+    let client = reqwest::Client::new();
+    let resp = server.post(&client, path).json(body).send().await?;
+    if !resp.status().is_success() {
+        let status_code = resp.status().as_u16();
+        let error = resp.json::<ApiError>().await.unwrap_or_else(|_| ApiError {
+            kind: ApiErrorKind::from_status_code(status_code),
+            message: format!("server responded with status {status_code}"),
+        });
+        return Err(error.into());
+    }
+    let resp = resp.json::<R>().await?;
 
-        Ok(resp)
+    Ok(resp)
+}
+
+/// Calls an OpenAI-compatible chat completions API, returns the content of the first choice.
+#[cfg(not(test))]
+async fn chat_completion(
+    direct: &DirectConfig,
+    messages: Vec<hey_cli_common::ChatMessage>,
+) -> Result<String> {
+    use hey_cli_common::{ApiError, ApiErrorKind, ChatMessage};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    struct ChatCompletionRequest<'a> {
+        model: &'a str,
+        messages: Vec<ChatMessage>,
+        max_tokens: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct ChatCompletionResponse {
+        choices: Vec<ChatCompletionChoice>,
+    }
+
+    #[derive(Deserialize)]
+    struct ChatCompletionChoice {
+        message: ChatCompletionMessage,
+    }
+
+    #[derive(Deserialize)]
+    struct ChatCompletionMessage {
+        content: Option<String>,
+    }
+
+    let url = format!("{}/chat/completions", direct.base_url);
+    let body = ChatCompletionRequest {
+        model: &direct.model,
+        messages,
+        max_tokens: 512,
+    };
+
+    let client = reqwest::Client::new();
+    let mut request = client.post(url).json(&body);
+    if let Some(api_key) = &direct.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    let resp = request.send().await?;
+    if !resp.status().is_success() {
+        let status_code = resp.status().as_u16();
+        return Err(ApiError {
+            kind: ApiErrorKind::from_status_code(status_code),
+            message: format!("model API responded with status {status_code}"),
+        }
+        .into());
+    }
+    let resp = resp.json::<ChatCompletionResponse>().await?;
+
+    let completion = resp
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+
+    Ok(completion)
+}
+
+#[cfg(not(test))]
+fn empty_model_response() -> hey_cli_common::ApiError {
+    hey_cli_common::ApiError {
+        kind: hey_cli_common::ApiErrorKind::ModelUnavailable,
+        message: "model returned an empty response".to_string(),
     }
 }
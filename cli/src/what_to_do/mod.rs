@@ -1,5 +1,6 @@
 use crate::{
-    check_ask::CheckAsk, check_setup::CheckSetup, reset::Reset, setup_script::SetupScript,
+    check_ask::CheckAsk, check_setup::CheckSetup, explain_command::ExplainCommand,
+    history::History, replay::Replay, reset::Reset, setup_script::SetupScript,
 };
 use nest_struct::nest_struct;
 
@@ -18,9 +19,13 @@ pub enum WhatToDoAfterParseArgs {
         },
     },
     CheckSetup(CheckSetup),
+    /// Nothing gets injected, so the setup script is not needed.
+    ExplainCommand(ExplainCommand),
+    History(History),
+    Replay(Replay),
 }
 
 pub enum WhatToDoAfterCheckSetup {
     SetupScript(SetupScript),
-    CheckAsk(CheckAsk),
+    CheckAsk(Box<CheckAsk>),
 }
@@ -1,18 +1,578 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CliPrompt {
     pub value: String,
+    /// A few words telling candidates apart, only set when more than one is asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Breakdown of each flag and pipe stage, only set when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct GetCliPromptResponse {
+    /// The best candidate.
     pub prompt: CliPrompt,
+    /// The other candidates, best first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<CliPrompt>,
+    /// Version of the server's prompt template the candidates come from, unset for the built-in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl GetCliPromptResponse {
+    /// All candidates, best first.
+    pub fn candidates(self) -> Vec<CliPrompt> {
+        let mut candidates = vec![self.prompt];
+        candidates.extend(self.alternatives);
+        candidates
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetCliPromptRequestBody {
     pub ask: String,
     pub context: HashMap<String, HashMap<String, String>>,
+    /// How many candidates to generate, clamped to `1..=MAX_CANDIDATES`.
+    #[serde(default = "default_candidates")]
+    pub candidates: u8,
+    /// Whether to explain the generated commands.
+    #[serde(default)]
+    pub explain: bool,
+    /// From the `.hey.toml` of the user's project, eg: "use `just` for tasks".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instructions: Vec<String>,
+    /// The shell the command is for, eg: `fish`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
+fn default_candidates() -> u8 {
+    1
+}
+
+pub const MAX_CANDIDATES: u8 = 5;
+
+/// Asks longer than this are rejected by both the CLI and the server.
+pub const MAX_ASK_LENGTH: usize = 100;
+
+/// Instructions adding up to more than this are rejected by the server.
+pub const MAX_INSTRUCTIONS_LENGTH: usize = 2000;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExplainCommandRequestBody {
+    pub command: String,
+    pub context: HashMap<String, HashMap<String, String>>,
+}
+
+/// A program, flag with its value, pipe or redirect, as written in the command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CommandPart {
+    pub token: String,
+    pub explain: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExplainCommandResponse {
+    pub summary: String,
+    pub parts: Vec<CommandPart>,
+}
+
+/// Commands longer than this are rejected by both the CLI and the server.
+pub const MAX_COMMAND_LENGTH: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorKind {
+    InvalidAsk,
+    RateLimited,
+    ModelUnavailable,
+    Internal,
+}
+
+impl ApiErrorKind {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ApiErrorKind::InvalidAsk => 400,
+            ApiErrorKind::RateLimited => 429,
+            ApiErrorKind::ModelUnavailable => 503,
+            ApiErrorKind::Internal => 500,
+        }
+    }
+
+    /// Best guess for responses without an [`ApiError`] body, eg: from a proxy in front of the server.
+    pub fn from_status_code(status_code: u16) -> Self {
+        match status_code {
+            400 | 413 | 422 => ApiErrorKind::InvalidAsk,
+            429 => ApiErrorKind::RateLimited,
+            502..=504 => ApiErrorKind::ModelUnavailable,
+            _ => ApiErrorKind::Internal,
+        }
+    }
+}
+
+/// Body of every non-successful response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+}
+
+/// A message in the format of OpenAI-compatible chat completion APIs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+}
+
+impl GetCliPromptRequestBody {
+    /// The messages sent to the model, shared by hey-cli-server and the CLI's direct backend.
+    pub fn to_messages(&self) -> Vec<ChatMessage> {
+        let mut messages = vec![
+            ChatMessage::system(
+                "The user will give you some context in form of JSON, then right after, the user will ask a question, and your job is to model the answer in a command line interface.",
+            ),
+            ChatMessage::system(self.response_format()),
+            ChatMessage::system(
+                "In the case where you don't have an answer, you can respond with `echo \"[your excuse]\"`",
+            ),
+        ];
+        messages.extend(self.instructions_message());
+        messages.extend([
+            ChatMessage::user(format!(
+                r#"user context:
+```json
+{}
+```
+"#,
+                serde_json::to_string(&self.context).expect("Failed to serialize context")
+            )),
+            ChatMessage::user(format!(
+                r#"user ask:
+{}
+"#,
+                self.ask
+            )),
+        ]);
+        messages
+    }
+
+    /// How the model must answer for [`Self::parse_completion`] to understand it.
+    pub fn response_format(&self) -> String {
+        match (self.candidate_count(), self.explain) {
+            (1, false) => "Your response must be a one-liner valid command that can be run in a shell. no extra, no code blocks.".to_string(),
+            (count, explain) => {
+                let mut fields = vec![
+                    r#"a "command" which is a one-liner valid command that can be run in a shell"#,
+                ];
+                if count > 1 {
+                    fields.push(r#"a "label" of a few words describing the approach"#);
+                }
+                if explain {
+                    fields.push(
+                        r#"an "explain" string breaking down each flag and pipe stage of the command, one per line, for someone who never used it"#,
+                    );
+                }
+                format!(
+                    "Your response must be a JSON array of up to {count} alternative answers, best first, each being an object with {}. no extra, no code blocks.",
+                    fields.join(", and ")
+                )
+            }
+        }
+    }
+
+    /// The project's instructions as a system message, `None` without any.
+    pub fn instructions_message(&self) -> Option<ChatMessage> {
+        if self.instructions.is_empty() {
+            return None;
+        }
+        Some(ChatMessage::system(format!(
+            "The user's project comes with these instructions, follow them when they apply to the ask:\n{}",
+            self.instructions
+                .iter()
+                .map(|instruction| format!("- {instruction}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )))
+    }
+
+    pub fn candidate_count(&self) -> u8 {
+        self.candidates.clamp(1, MAX_CANDIDATES)
+    }
+
+    /// Same for asks differing only in case, spacing or trailing punctuation,
+    /// eg: `Show disk usage?` and `show  disk usage`, with the same context.
+    pub fn cache_key(&self) -> String {
+        let ask = self
+            .ask
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let ask = ask.trim_end_matches(['?', '!', '.']).trim_end();
+        let context = self
+            .context
+            .iter()
+            .map(|(section, values)| (section, values.iter().collect::<BTreeMap<_, _>>()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut key = format!(
+            "{ask}\n{}\n{}\n{}",
+            serde_json::to_string(&context).expect("Failed to serialize context"),
+            self.candidate_count(),
+            self.explain
+        );
+        // appended only when set, to keep the keys of asks from older clients unchanged
+        if !self.instructions.is_empty() {
+            key.push('\n');
+            key.push_str(&self.instructions.join("\n"));
+        }
+        if let Some(shell) = &self.shell {
+            key.push_str("\nshell: ");
+            key.push_str(shell);
+        }
+        stable_hash(&key)
+    }
+
+    /// Parses the model's answer to the messages from [`Self::to_messages`],
+    /// returns `None` when it holds no command.
+    pub fn parse_completion(&self, completion: &str) -> Option<GetCliPromptResponse> {
+        #[derive(Deserialize)]
+        struct Candidate {
+            label: Option<String>,
+            command: String,
+            explain: Option<String>,
+        }
+
+        let completion = strip_code_block(completion);
+        let parsed = match (self.candidate_count(), self.explain) {
+            (1, false) => None,
+            _ => serde_json::from_str::<Vec<Candidate>>(completion).ok(),
+        };
+
+        let candidates = match parsed {
+            Some(parsed) => parsed
+                .into_iter()
+                .filter(|candidate| !candidate.command.trim().is_empty())
+                .take(self.candidate_count().into())
+                .map(|candidate| CliPrompt {
+                    value: candidate.command.trim().to_string(),
+                    label: candidate.label,
+                    explain: candidate
+                        .explain
+                        .filter(|explain| !explain.trim().is_empty()),
+                })
+                .collect(),
+            None if completion.is_empty() => vec![],
+            // models don't always follow the format, in which case the whole answer is the command
+            None => vec![CliPrompt {
+                value: completion.to_string(),
+                label: None,
+                explain: None,
+            }],
+        };
+
+        let mut candidates = candidates.into_iter();
+        Some(GetCliPromptResponse {
+            prompt: candidates.next()?,
+            alternatives: candidates.collect(),
+            template_version: None,
+        })
+    }
+}
+
+impl ExplainCommandRequestBody {
+    /// The messages sent to the model, shared by hey-cli-server and the CLI's direct backend.
+    pub fn to_messages(&self) -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system(
+                "The user will give you some context in form of JSON, then right after, the user will give you a shell command, and your job is to explain what it does to someone who never used it.",
+            ),
+            ChatMessage::system(
+                r#"Your response must be a JSON object with a "summary" of one sentence, and "parts" which is an array of objects, in the order they appear in the command, each with a "token" which is a program, a flag with its value, a pipe or a redirect exactly as written in the command, and an "explain" of a few words. no extra, no code blocks."#,
+            ),
+            ChatMessage::user(format!(
+                r#"user context:
+```json
+{}
+```
+"#,
+                serde_json::to_string(&self.context).expect("Failed to serialize context")
+            )),
+            ChatMessage::user(format!(
+                r#"user command:
+{}
+"#,
+                self.command
+            )),
+        ]
+    }
+
+    /// Parses the model's answer to the messages from [`Self::to_messages`],
+    /// returns `None` when it holds no explanation.
+    pub fn parse_completion(&self, completion: &str) -> Option<ExplainCommandResponse> {
+        let completion = strip_code_block(completion);
+        if completion.is_empty() {
+            return None;
+        }
+
+        // models don't always follow the format, in which case the whole answer is the summary
+        let response = serde_json::from_str::<ExplainCommandResponse>(completion).unwrap_or(
+            ExplainCommandResponse {
+                summary: completion.to_string(),
+                parts: vec![],
+            },
+        );
+        Some(response)
+    }
+}
+
+/// FNV-1a as 16 hex characters, stable across runs and Rust versions unlike `DefaultHasher`.
+pub fn stable_hash(value: &str) -> String {
+    let hash = value.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Removes surrounding whitespace and markdown code fences, if any.
+fn strip_code_block(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```") {
+        Some(fenced) => {
+            // skip the info string, eg: ```json
+            let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+            body.trim_end().trim_end_matches("```").trim()
+        }
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(candidates: u8) -> GetCliPromptRequestBody {
+        GetCliPromptRequestBody {
+            ask: "list files".to_string(),
+            context: HashMap::new(),
+            candidates,
+            explain: false,
+            instructions: vec![],
+            shell: None,
+        }
+    }
+
+    #[test]
+    fn single_candidate_is_the_whole_completion() {
+        let response = query(1).parse_completion("  ls -la\n").unwrap();
+        assert_eq!(response.prompt.value, "ls -la");
+        assert!(response.alternatives.is_empty());
+    }
+
+    #[test]
+    fn multiple_candidates_are_parsed_in_order() {
+        let completion = r#"```json
+[
+    {"label": "long listing", "command": "ls -la"},
+    {"label": "tree", "command": "tree -L 1"},
+    {"label": "empty", "command": " "}
+]
+```"#;
+        let candidates = query(3).parse_completion(completion).unwrap().candidates();
+        assert_eq!(
+            candidates,
+            vec![
+                CliPrompt {
+                    value: "ls -la".to_string(),
+                    label: Some("long listing".to_string()),
+                    explain: None,
+                },
+                CliPrompt {
+                    value: "tree -L 1".to_string(),
+                    label: Some("tree".to_string()),
+                    explain: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn single_candidate_with_explanation() {
+        let query = GetCliPromptRequestBody {
+            explain: true,
+            ..query(1)
+        };
+        let completion = r#"[{"command": "ls -la", "explain": "ls: list files\n-l: long format\n-a: include hidden files"}]"#;
+        let response = query.parse_completion(completion).unwrap();
+        assert_eq!(response.prompt.value, "ls -la");
+        assert_eq!(
+            response.prompt.explain.as_deref(),
+            Some("ls: list files\n-l: long format\n-a: include hidden files")
+        );
+        assert!(query.to_messages()[1].content.contains(r#""explain""#));
+    }
+
+    #[test]
+    fn multiple_candidates_fall_back_to_the_whole_completion() {
+        let response = query(3).parse_completion("ls -la").unwrap();
+        assert_eq!(response.prompt.value, "ls -la");
+        assert!(response.alternatives.is_empty());
+    }
+
+    #[test]
+    fn empty_completion_has_no_candidates() {
+        assert!(query(1).parse_completion(" \n").is_none());
+        assert!(query(3).parse_completion("[]").is_none());
+    }
+
+    #[test]
+    fn command_explanation() {
+        let query = ExplainCommandRequestBody {
+            command: "tar -xzvf foo.tgz -C /opt".to_string(),
+            context: HashMap::new(),
+        };
+        let completion = r#"{"summary": "Extracts foo.tgz into /opt", "parts": [
+            {"token": "tar", "explain": "archive utility"},
+            {"token": "-xzvf", "explain": "extract gzipped file verbosely"},
+            {"token": "foo.tgz", "explain": "the archive"},
+            {"token": "-C /opt", "explain": "extract into /opt"}
+        ]}"#;
+        let response = query.parse_completion(completion).unwrap();
+        assert_eq!(response.summary, "Extracts foo.tgz into /opt");
+        assert_eq!(response.parts.len(), 4);
+        assert_eq!(response.parts[3].token, "-C /opt");
+
+        let response = query.parse_completion("It extracts an archive").unwrap();
+        assert_eq!(response.summary, "It extracts an archive");
+        assert!(response.parts.is_empty());
+
+        assert!(query.parse_completion("").is_none());
+    }
+
+    #[test]
+    fn candidates_are_clamped() {
+        assert_eq!(query(0).candidate_count(), 1);
+        assert_eq!(query(u8::MAX).candidate_count(), MAX_CANDIDATES);
+    }
+
+    #[test]
+    fn old_clients_get_a_single_candidate() {
+        let query =
+            serde_json::from_str::<GetCliPromptRequestBody>(r#"{"ask": "ls", "context": {}}"#)
+                .unwrap();
+        assert_eq!(query.candidate_count(), 1);
+    }
+
+    #[test]
+    fn cache_keys_normalize_the_ask_and_context_order() {
+        let with = |ask: &str, context: &[(&str, &str)], candidates: u8, explain: bool| {
+            let general = context
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            GetCliPromptRequestBody {
+                ask: ask.to_string(),
+                context: HashMap::from([("general".to_string(), general)]),
+                candidates,
+                explain,
+                instructions: vec![],
+                shell: None,
+            }
+            .cache_key()
+        };
+        let context = [("os_type", "Mac OS"), ("os_bitness", "64-bit")];
+
+        let key = with("show disk usage", &context, 1, false);
+        assert_eq!(key.len(), 16);
+        assert_eq!(key, with("  Show   DISK usage? ", &context, 1, false));
+        assert_eq!(key, with("show disk usage!!", &context, 0, false));
+        assert_eq!(
+            key,
+            with("show disk usage", &[context[1], context[0]], 1, false)
+        );
+        assert_ne!(key, with("show memory usage", &context, 1, false));
+        assert_ne!(key, with("show disk usage", &context[..1], 1, false));
+        assert_ne!(key, with("show disk usage", &context, 3, false));
+        assert_ne!(key, with("show disk usage", &context, 1, true));
+    }
+
+    #[test]
+    fn project_instructions_are_system_messages() {
+        let with_instructions = GetCliPromptRequestBody {
+            instructions: vec![
+                "use `just` for tasks".to_string(),
+                "deploy with `kamal`".to_string(),
+            ],
+            ..query(1)
+        };
+
+        let messages = with_instructions.to_messages();
+        assert_eq!(messages.len(), query(1).to_messages().len() + 1);
+        let instructions = messages
+            .iter()
+            .find(|message| message.content.contains("instructions"))
+            .unwrap();
+        assert_eq!(instructions.role, ChatRole::System);
+        assert!(
+            instructions
+                .content
+                .ends_with(":\n- use `just` for tasks\n- deploy with `kamal`")
+        );
+        assert_eq!(messages.last().unwrap().content, "user ask:\nlist files\n");
+
+        assert_ne!(with_instructions.cache_key(), query(1).cache_key());
+    }
+
+    #[test]
+    fn shell_is_part_of_the_cache_key() {
+        let with_shell = |shell: &str| GetCliPromptRequestBody {
+            shell: Some(shell.to_string()),
+            ..query(1)
+        };
+        assert_ne!(with_shell("fish").cache_key(), query(1).cache_key());
+        assert_ne!(
+            with_shell("fish").cache_key(),
+            with_shell("bash").cache_key()
+        );
+        assert_eq!(
+            with_shell("fish").cache_key(),
+            with_shell("fish").cache_key()
+        );
+    }
 }
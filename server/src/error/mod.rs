@@ -0,0 +1,101 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use hey_cli_common::{ApiError, ApiErrorKind};
+
+#[derive(Debug, Clone)]
+pub enum ServerError {
+    InvalidAsk(String),
+    RateLimited,
+    ModelUnavailable(String),
+    Internal(String),
+}
+
+impl ServerError {
+    pub fn to_api_error(&self) -> ApiError {
+        let (kind, message) = match self {
+            ServerError::InvalidAsk(message) => (ApiErrorKind::InvalidAsk, message.clone()),
+            ServerError::RateLimited => (
+                ApiErrorKind::RateLimited,
+                "Too many requests to the model".to_string(),
+            ),
+            ServerError::ModelUnavailable(message) => {
+                (ApiErrorKind::ModelUnavailable, message.clone())
+            }
+            ServerError::Internal(message) => (ApiErrorKind::Internal, message.clone()),
+        };
+
+        ApiError { kind, message }
+    }
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_api_error())
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let error = self.to_api_error();
+        let status_code = StatusCode::from_u16(error.kind.status_code())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        match &self {
+            ServerError::InvalidAsk(_) => tracing::info!("{self}"),
+            _ => tracing::error!("{self}"),
+        }
+
+        (status_code, Json(error)).into_response()
+    }
+}
+
+impl From<async_openai::error::OpenAIError> for ServerError {
+    fn from(error: async_openai::error::OpenAIError) -> Self {
+        use async_openai::error::OpenAIError;
+
+        match error {
+            OpenAIError::ApiError(api_error) => {
+                let code = api_error.code.as_deref().unwrap_or_default();
+                let kind = api_error.r#type.as_deref().unwrap_or_default();
+                if code == "rate_limit_exceeded" || kind == "requests" || kind == "tokens" {
+                    ServerError::RateLimited
+                } else {
+                    ServerError::ModelUnavailable(api_error.message)
+                }
+            }
+            OpenAIError::Reqwest(error) => error.into(),
+            OpenAIError::InvalidArgument(message) => ServerError::Internal(message),
+            error => ServerError::ModelUnavailable(error.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ServerError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.status().map(|status| status.as_u16()) {
+            Some(429) => ServerError::RateLimited,
+            _ => ServerError::ModelUnavailable(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_codes_match_the_common_error_kinds() {
+        let cases = [
+            (ServerError::InvalidAsk("empty".to_string()), 400),
+            (ServerError::RateLimited, 429),
+            (ServerError::ModelUnavailable("down".to_string()), 503),
+            (ServerError::Internal("oops".to_string()), 500),
+        ];
+        for (error, status_code) in cases {
+            assert_eq!(error.into_response().status().as_u16(), status_code);
+        }
+    }
+}
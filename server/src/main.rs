@@ -1,20 +1,32 @@
-use async_openai::{
-    Client,
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
-        CreateChatCompletionRequestArgs,
-    },
-};
+mod error;
+mod prompt_cache;
+mod prompt_generator;
+mod prompt_templates;
+
 use axum::{
     Json, Router,
+    extract::{State, rejection::JsonRejection},
     routing::{get, post},
 };
 use dotenv::dotenv;
-use hey_cli_common::{CliPrompt, GetCliPromptRequestBody, GetCliPromptResponse};
-use std::collections::HashMap;
+use error::ServerError;
+use hey_cli_common::{
+    ExplainCommandRequestBody, ExplainCommandResponse, GetCliPromptRequestBody,
+    GetCliPromptResponse, MAX_ASK_LENGTH, MAX_COMMAND_LENGTH, MAX_INSTRUCTIONS_LENGTH, stable_hash,
+};
+use prompt_cache::{CacheStats, PromptCache};
+use prompt_generator::PromptGenerator;
+use prompt_templates::{PromptTemplates, Template};
+use std::sync::Arc;
 use tracing_subscriber::prelude::*;
 
+#[derive(Clone)]
+struct AppState {
+    prompt_generator: Arc<dyn PromptGenerator>,
+    prompt_cache: Arc<PromptCache>,
+    prompt_templates: Arc<PromptTemplates>,
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
@@ -23,11 +35,29 @@ async fn main() {
 
     ts.init();
 
+    let prompt_generator = prompt_generator::from_env(|key| std::env::var(key).ok())
+        .expect("Failed to configure the prompt generator");
+    let prompt_cache = PromptCache::from_env(|key| std::env::var(key).ok())
+        .expect("Failed to configure the prompt cache");
+    let prompt_templates = PromptTemplates::from_env(|key| std::env::var(key).ok())
+        .expect("Failed to configure the prompt templates");
+    let prompt_templates = Arc::new(prompt_templates);
+    prompt_templates.clone().watch();
+    let state = AppState {
+        prompt_generator,
+        prompt_cache: Arc::new(prompt_cache),
+        prompt_templates,
+    };
+
     let app = Router::new()
         .route("/", get(root))
         .route("/cli-prompt", post(post_cli_prompt))
+        .route("/explain-command", post(post_explain_command))
         .route("/health", get(health))
-        .route("/install.sh", get(get_install_script));
+        .route("/cache-stats", get(get_cache_stats))
+        .route("/install.sh", get(get_install_script))
+        .route("/install.ps1", get(get_install_power_shell_script))
+        .with_state(state);
 
     let fallback_port = "3000";
     let port = std::env::var("PORT").unwrap_or(fallback_port.to_string());
@@ -39,70 +69,110 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
-#[tracing::instrument]
-async fn post_cli_prompt(query: Json<GetCliPromptRequestBody>) -> Json<GetCliPromptResponse> {
-    let prompt = generate_cli_prompt(&query.ask, &query.context).await;
+#[tracing::instrument(skip(state))]
+async fn post_cli_prompt(
+    State(state): State<AppState>,
+    query: Result<Json<GetCliPromptRequestBody>, JsonRejection>,
+) -> Result<Json<GetCliPromptResponse>, ServerError> {
+    let Json(query) = query.map_err(|rejection| ServerError::InvalidAsk(rejection.body_text()))?;
+    check_ask(&query.ask)?;
+    check_instructions(&query.instructions)?;
+
+    let template = state.prompt_templates.select(&query);
+    // a new template version must not be answered with commands of the previous one
+    let cache_key = match &template {
+        Some(template) => stable_hash(&format!("{}\n{}", query.cache_key(), template.version)),
+        None => query.cache_key(),
+    };
+
+    let response = state
+        .prompt_cache
+        .get_or_generate(cache_key, || {
+            generate_cli_prompt(state.prompt_generator.as_ref(), &query, template.as_deref())
+        })
+        .await?;
 
-    Json(GetCliPromptResponse { prompt })
+    Ok(Json(response))
 }
 
-// TODO: add anyhow error handling
-#[tracing::instrument(ret)]
+fn check_ask(ask: &str) -> Result<(), ServerError> {
+    if ask.trim().is_empty() {
+        return Err(ServerError::InvalidAsk("ask is empty".to_string()));
+    }
+    if ask.contains('\n') {
+        return Err(ServerError::InvalidAsk(
+            "new line character is not allowed".to_string(),
+        ));
+    }
+    if ask.len() > MAX_ASK_LENGTH {
+        return Err(ServerError::InvalidAsk(format!(
+            "max length of {MAX_ASK_LENGTH} characters reached"
+        )));
+    }
+    Ok(())
+}
+
+fn check_instructions(instructions: &[String]) -> Result<(), ServerError> {
+    if instructions.iter().map(String::len).sum::<usize>() > MAX_INSTRUCTIONS_LENGTH {
+        return Err(ServerError::InvalidAsk(format!(
+            "instructions max length of {MAX_INSTRUCTIONS_LENGTH} characters reached"
+        )));
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip(prompt_generator, template), ret)]
 async fn generate_cli_prompt(
-    user_message: &String,
-    context: &HashMap<String, HashMap<String, String>>,
-) -> CliPrompt {
-    let openai_key = std::env::var("OPENAI_KEY").unwrap();
-    let openai_organization_id = std::env::var("OPENAI_ORGANIZATION_ID").unwrap();
-
-    // TODO: save the client in a global state
-    let config = OpenAIConfig::new()
-        .with_api_key(openai_key)
-        .with_org_id(openai_organization_id);
-
-    let client = Client::with_config(config);
-
-    let messages = [
-        ChatCompletionRequestSystemMessage::from(
-            "The user will give you some context in form of JSON, then right after, the user will ask a question, and your job is to model the answer in a command line interface.",
-        )
-        .into(),
-        ChatCompletionRequestSystemMessage::from(
-            "Your response must be a one-liner valid command that can be run in a shell. no extra, no code blocks.",
-        )
-        .into(),
-        ChatCompletionRequestSystemMessage::from(
-            "In the case where you don't have an answer, you can respond with `echo \"[your excuse]\"`",
-        )
-        .into(),
-        ChatCompletionRequestUserMessage::from(format!(r#"user context:
-```json
-{}
-```
-"#, serde_json::to_string(context).expect("Failed to serialize context"))).into(),
-        ChatCompletionRequestUserMessage::from(format!(r#"user ask:
-{}
-"#, user_message.clone())).into(),
-    ];
-
-    let request = CreateChatCompletionRequestArgs::default()
-        .max_tokens(512u32)
-        .model("gpt-4o-2024-08-06")
-        .messages(messages)
-        .build()
-        .expect("Failed to build request");
-
-    let response = client
-        .chat()
-        .create(request)
-        .await
-        .expect("Failed to create chat completion");
-
-    let value = response.choices.first().unwrap();
-    let value = &value.message.content;
-    let value = value.clone().unwrap();
-
-    CliPrompt { value }
+    prompt_generator: &dyn PromptGenerator,
+    query: &GetCliPromptRequestBody,
+    template: Option<&Template>,
+) -> Result<GetCliPromptResponse, ServerError> {
+    let messages = match template {
+        Some(template) => template.render(query),
+        None => query.to_messages(),
+    };
+
+    let completion = prompt_generator.generate(&messages).await?;
+
+    let mut response = query
+        .parse_completion(&completion)
+        .ok_or(ServerError::ModelUnavailable(
+            "Model returned an empty response".to_string(),
+        ))?;
+    response.template_version = template.map(|template| template.version.clone());
+    Ok(response)
+}
+
+#[tracing::instrument(skip(state))]
+async fn post_explain_command(
+    State(state): State<AppState>,
+    query: Result<Json<ExplainCommandRequestBody>, JsonRejection>,
+) -> Result<Json<ExplainCommandResponse>, ServerError> {
+    let Json(query) = query.map_err(|rejection| ServerError::InvalidAsk(rejection.body_text()))?;
+    check_command(&query.command)?;
+
+    let messages = query.to_messages();
+    let completion = state.prompt_generator.generate(&messages).await?;
+
+    let response = query
+        .parse_completion(&completion)
+        .ok_or(ServerError::ModelUnavailable(
+            "Model returned an empty response".to_string(),
+        ))?;
+
+    Ok(Json(response))
+}
+
+fn check_command(command: &str) -> Result<(), ServerError> {
+    if command.trim().is_empty() {
+        return Err(ServerError::InvalidAsk("command is empty".to_string()));
+    }
+    if command.len() > MAX_COMMAND_LENGTH {
+        return Err(ServerError::InvalidAsk(format!(
+            "max length of {MAX_COMMAND_LENGTH} characters reached"
+        )));
+    }
+    Ok(())
 }
 
 #[tracing::instrument]
@@ -111,15 +181,148 @@ async fn health() -> &'static str {
     "OK"
 }
 
+#[tracing::instrument(skip(state))]
+async fn get_cache_stats(State(state): State<AppState>) -> Json<CacheStats> {
+    Json(state.prompt_cache.stats())
+}
+
 #[tracing::instrument]
 async fn get_install_script() -> &'static str {
     let content = include_str!("scripts/install.sh");
     content
 }
 
+#[tracing::instrument]
+async fn get_install_power_shell_script() -> &'static str {
+    let content = include_str!("scripts/install.ps1");
+    content
+}
+
 const HTML: &str = include_str!("./home.html");
 
 #[tracing::instrument]
 async fn root() -> axum::response::Html<&'static str> {
     axum::response::Html(HTML)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use std::{collections::HashMap, time::Duration};
+
+    fn state() -> State<AppState> {
+        State(AppState {
+            prompt_generator: Arc::new(prompt_generator::Mock),
+            prompt_cache: Arc::new(PromptCache::new(10, 60, None)),
+            prompt_templates: Arc::new(PromptTemplates::new(None, Duration::ZERO).unwrap()),
+        })
+    }
+
+    fn query(ask: &str) -> Result<Json<GetCliPromptRequestBody>, JsonRejection> {
+        Ok(Json(GetCliPromptRequestBody {
+            ask: ask.to_string(),
+            context: HashMap::new(),
+            candidates: 1,
+            explain: false,
+            instructions: vec![],
+            shell: None,
+        }))
+    }
+
+    #[tokio::test]
+    async fn cli_prompt() {
+        let Json(response) = post_cli_prompt(state(), query("print working directory"))
+            .await
+            .unwrap();
+        assert_eq!(response.prompt.value, r#"echo "print working directory""#);
+    }
+
+    #[tokio::test]
+    async fn cli_prompt_invalid_ask() {
+        let long_ask = "a".repeat(MAX_ASK_LENGTH + 1);
+        for ask in ["", "print\nworking directory", &long_ask] {
+            let response = post_cli_prompt(state(), query(ask))
+                .await
+                .unwrap_err()
+                .into_response();
+            assert_eq!(response.status().as_u16(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn cli_prompt_instructions() {
+        let with_instructions = |instructions: Vec<String>| {
+            query("deploy").map(|Json(query)| {
+                Json(GetCliPromptRequestBody {
+                    instructions,
+                    ..query
+                })
+            })
+        };
+
+        let instructions = vec!["deploy with `kamal`".to_string()];
+        assert!(
+            post_cli_prompt(state(), with_instructions(instructions))
+                .await
+                .is_ok()
+        );
+
+        let instructions = vec!["a".repeat(MAX_INSTRUCTIONS_LENGTH / 2 + 1); 2];
+        let response = post_cli_prompt(state(), with_instructions(instructions))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    #[tokio::test]
+    async fn cli_prompt_template() {
+        let dir = std::env::temp_dir().join(format!(
+            "hey-cli-server-test-templates-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("terse.toml"),
+            "version = \"terse-1\"\n[[messages]]\nrole = \"system\"\ncontent = \"{{response_format}}\"\n[[messages]]\nrole = \"user\"\ncontent = \"in {{shell}}: {{ask}}\"\n",
+        )
+        .unwrap();
+        let prompt_templates = PromptTemplates::new(Some(dir.clone()), Duration::ZERO).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let State(default_state) = state();
+        let templated_state = State(AppState {
+            prompt_templates: Arc::new(prompt_templates),
+            ..default_state
+        });
+        let Json(response) = post_cli_prompt(templated_state, query("list files"))
+            .await
+            .unwrap();
+        assert_eq!(response.prompt.value, r#"echo "in unknown: list files""#);
+        assert_eq!(response.template_version.as_deref(), Some("terse-1"));
+
+        let Json(response) = post_cli_prompt(state(), query("list files")).await.unwrap();
+        assert_eq!(response.template_version, None);
+    }
+
+    #[tokio::test]
+    async fn explain_command() {
+        let query = Ok(Json(ExplainCommandRequestBody {
+            command: "tar -xzvf foo.tgz -C /opt".to_string(),
+            context: HashMap::new(),
+        }));
+        let Json(response) = post_explain_command(state(), query).await.unwrap();
+        assert!(response.summary.contains("tar -xzvf foo.tgz -C /opt"));
+
+        let query = Ok(Json(ExplainCommandRequestBody {
+            command: " ".to_string(),
+            context: HashMap::new(),
+        }));
+        let response = post_explain_command(state(), query)
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
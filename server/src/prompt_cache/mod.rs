@@ -0,0 +1,464 @@
+use crate::error::ServerError;
+use anyhow::{Context, Result};
+use hey_cli_common::GetCliPromptResponse;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::OnceCell;
+
+type SharedResult = Arc<OnceCell<Result<GetCliPromptResponse, ServerError>>>;
+
+/// The disk directory is swept of expired and extra entries once per this many writes.
+const DISK_SWEEP_EVERY_WRITES: u64 = 100;
+
+/// Caches responses by [`hey_cli_common::GetCliPromptRequestBody::cache_key`] in an LRU,
+/// optionally backed by a directory, and makes identical concurrent asks share one model call.
+pub struct PromptCache {
+    capacity: usize,
+    ttl_secs: u64,
+    disk_dir: Option<PathBuf>,
+    /// Files kept in `disk_dir`, the least recently written are removed first.
+    disk_capacity: usize,
+    disk_writes: AtomicU64,
+    memory: Mutex<HashMap<String, MemoryEntry>>,
+    in_flight: Mutex<HashMap<String, SharedResult>>,
+    /// Incremented on every use, the entry with the lowest `last_used` is evicted first.
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+struct MemoryEntry {
+    stored: StoredResponse,
+    last_used: u64,
+}
+
+/// Also the content of each file in the disk backend.
+#[derive(Serialize, Deserialize, Clone)]
+struct StoredResponse {
+    /// Seconds since the unix epoch.
+    created_at: u64,
+    response: GetCliPromptResponse,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Asks answered by a model call already in flight for an identical ask.
+    pub coalesced: u64,
+    pub entries: usize,
+}
+
+impl PromptCache {
+    pub const DEFAULT_CAPACITY: usize = 1000;
+    pub const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+    pub const DEFAULT_DISK_CAPACITY: usize = 10_000;
+
+    /// A `capacity` of 0 disables the in-memory cache, identical asks are still coalesced.
+    pub fn new(capacity: usize, ttl_secs: u64, disk_dir: Option<PathBuf>) -> Self {
+        Self {
+            capacity,
+            ttl_secs,
+            disk_dir,
+            disk_capacity: Self::DEFAULT_DISK_CAPACITY,
+            disk_writes: AtomicU64::new(0),
+            memory: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    /// Configured by `PROMPT_CACHE_CAPACITY`, `PROMPT_CACHE_TTL_SECS`, and `PROMPT_CACHE_DIR`
+    /// to also keep responses on disk across restarts, up to `PROMPT_CACHE_DISK_CAPACITY` files.
+    pub fn from_env(env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let parse = |key: &str| {
+            env(key)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .with_context(|| format!("Invalid {key}: {value}"))
+                })
+                .transpose()
+        };
+
+        let cache = Self::new(
+            parse("PROMPT_CACHE_CAPACITY")?
+                .map_or(Self::DEFAULT_CAPACITY, |capacity| capacity as usize),
+            parse("PROMPT_CACHE_TTL_SECS")?.unwrap_or(Self::DEFAULT_TTL_SECS),
+            env("PROMPT_CACHE_DIR").map(PathBuf::from),
+        );
+        Ok(Self {
+            disk_capacity: parse("PROMPT_CACHE_DISK_CAPACITY")?
+                .map_or(Self::DEFAULT_DISK_CAPACITY, |capacity| capacity as usize),
+            ..cache
+        })
+    }
+
+    /// Errors are shared with the coalesced asks but never cached.
+    pub async fn get_or_generate<F, Fut>(
+        &self,
+        key: String,
+        generate: F,
+    ) -> Result<GetCliPromptResponse, ServerError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<GetCliPromptResponse, ServerError>>,
+    {
+        if let Some(response) = self.get(&key).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(response);
+        }
+
+        let shared = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let mut generated = false;
+        let result = shared
+            .get_or_init(|| {
+                generated = true;
+                generate()
+            })
+            .await
+            .clone();
+
+        if generated {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            if let Ok(response) = &result {
+                self.insert(&key, response.clone()).await;
+            }
+            self.in_flight.lock().unwrap().remove(&key);
+        } else {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            entries: self.memory.lock().unwrap().len(),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<GetCliPromptResponse> {
+        let now = unix_now();
+        if let Some(response) = self.get_from_memory(key, now) {
+            return Some(response);
+        }
+
+        let stored = self.read_from_disk(key).await?;
+        if self.is_expired(&stored, now) {
+            if let Some(disk_dir) = &self.disk_dir {
+                let _ = tokio::fs::remove_file(disk_dir.join(format!("{key}.json"))).await;
+            }
+            return None;
+        }
+        self.insert_in_memory(key, stored.clone());
+        Some(stored.response)
+    }
+
+    fn get_from_memory(&self, key: &str, now: u64) -> Option<GetCliPromptResponse> {
+        let last_used = self.clock.fetch_add(1, Ordering::Relaxed);
+
+        let mut memory = self.memory.lock().unwrap();
+        match memory.get_mut(key) {
+            Some(entry) if !self.is_expired(&entry.stored, now) => {
+                entry.last_used = last_used;
+                Some(entry.stored.response.clone())
+            }
+            Some(_) => {
+                memory.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn insert(&self, key: &str, response: GetCliPromptResponse) {
+        let stored = StoredResponse {
+            created_at: unix_now(),
+            response,
+        };
+        self.write_to_disk(key, &stored).await;
+        self.insert_in_memory(key, stored);
+    }
+
+    fn insert_in_memory(&self, key: &str, stored: StoredResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let last_used = self.clock.fetch_add(1, Ordering::Relaxed);
+        let mut memory = self.memory.lock().unwrap();
+        memory.insert(key.to_string(), MemoryEntry { stored, last_used });
+
+        while memory.len() > self.capacity {
+            let Some(least_recently_used) = memory
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            memory.remove(&least_recently_used);
+        }
+    }
+
+    fn is_expired(&self, stored: &StoredResponse, now: u64) -> bool {
+        now.saturating_sub(stored.created_at) >= self.ttl_secs
+    }
+
+    async fn read_from_disk(&self, key: &str) -> Option<StoredResponse> {
+        let path = self.disk_dir.as_ref()?.join(format!("{key}.json"));
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// The disk is only a fallback, failing to write to it never fails the ask.
+    async fn write_to_disk(&self, key: &str, stored: &StoredResponse) {
+        let Some(disk_dir) = &self.disk_dir else {
+            return;
+        };
+        let written = async {
+            tokio::fs::create_dir_all(disk_dir).await?;
+            let content = serde_json::to_string(stored).map_err(std::io::Error::other)?;
+            tokio::fs::write(disk_dir.join(format!("{key}.json")), content).await
+        };
+        if let Err(error) = written.await {
+            tracing::warn!("Could not write to the prompt cache directory: {error}");
+        }
+
+        let writes = self.disk_writes.fetch_add(1, Ordering::Relaxed) + 1;
+        if writes % DISK_SWEEP_EVERY_WRITES == 1 {
+            let (disk_dir, ttl_secs, capacity) =
+                (disk_dir.clone(), self.ttl_secs, self.disk_capacity);
+            // in the background, the ask doesn't wait for a directory listing
+            tokio::task::spawn_blocking(move || sweep_disk(&disk_dir, ttl_secs, capacity));
+        }
+    }
+}
+
+/// Removes the expired files, then the least recently written ones past `capacity`,
+/// going by their modification time rather than reading each of them.
+fn sweep_disk(disk_dir: &Path, ttl_secs: u64, capacity: usize) {
+    let Ok(entries) = std::fs::read_dir(disk_dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    let mut files = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified());
+            Some((modified.ok()?, path))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let expired = files.partition_point(|(modified, _)| {
+        now.duration_since(*modified).unwrap_or_default() >= Duration::from_secs(ttl_secs)
+    });
+    let removed = expired.max(files.len().saturating_sub(capacity));
+    for (_, path) in &files[..removed] {
+        if let Err(error) = std::fs::remove_file(path) {
+            tracing::warn!("Could not remove from the prompt cache directory: {error}");
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hey_cli_common::CliPrompt;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::Notify;
+
+    fn response(value: &str) -> GetCliPromptResponse {
+        GetCliPromptResponse {
+            prompt: CliPrompt {
+                value: value.to_string(),
+                label: None,
+                explain: None,
+            },
+            alternatives: vec![],
+            template_version: None,
+        }
+    }
+
+    async fn ask(cache: &PromptCache, key: &str, value: &str) -> GetCliPromptResponse {
+        let value = value.to_string();
+        cache
+            .get_or_generate(key.to_string(), || async move { Ok(response(&value)) })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn hits_and_evicts_least_recently_used() {
+        let cache = PromptCache::new(2, 60, None);
+        assert_eq!(ask(&cache, "a", "1").await, response("1"));
+        assert_eq!(ask(&cache, "a", "2").await, response("1"));
+
+        ask(&cache, "b", "3").await;
+        ask(&cache, "a", "4").await;
+        ask(&cache, "c", "5").await;
+        assert_eq!(ask(&cache, "a", "6").await, response("1"));
+        assert_eq!(ask(&cache, "b", "7").await, response("7"));
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 4,
+                coalesced: 0,
+                entries: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_generated_again() {
+        let cache = PromptCache::new(10, 0, None);
+        ask(&cache, "a", "1").await;
+        assert_eq!(ask(&cache, "a", "2").await, response("2"));
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = PromptCache::new(10, 60, None);
+        let error = cache
+            .get_or_generate("a".to_string(), || async { Err(ServerError::RateLimited) })
+            .await;
+        assert!(matches!(error, Err(ServerError::RateLimited)));
+        assert_eq!(ask(&cache, "a", "1").await, response("1"));
+    }
+
+    #[tokio::test]
+    async fn identical_concurrent_asks_share_one_call() {
+        let cache = PromptCache::new(10, 60, None);
+        let calls = &AtomicUsize::new(0);
+        let release = &Notify::new();
+
+        let generate = move || async move {
+            calls.fetch_add(1, Ordering::Relaxed);
+            release.notified().await;
+            Ok(response("ls"))
+        };
+        let (first, second, _) = tokio::join!(
+            cache.get_or_generate("a".to_string(), generate),
+            cache.get_or_generate("a".to_string(), generate),
+            async {
+                tokio::task::yield_now().await;
+                release.notify_one();
+            },
+        );
+
+        assert_eq!(first.unwrap(), response("ls"));
+        assert_eq!(second.unwrap(), response("ls"));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.stats().coalesced, 1);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn disk_backend_survives_restarts() {
+        let disk_dir =
+            std::env::temp_dir().join(format!("hey-cli-prompt-cache-test-{}", std::process::id()));
+
+        let cache = PromptCache::new(10, 60, Some(disk_dir.clone()));
+        ask(&cache, "a", "1").await;
+
+        let restarted = PromptCache::new(0, 60, Some(disk_dir.clone()));
+        assert_eq!(ask(&restarted, "a", "2").await, response("1"));
+        assert_eq!(restarted.stats().hits, 1);
+
+        std::fs::remove_dir_all(disk_dir).unwrap();
+    }
+
+    #[test]
+    fn disk_sweep_removes_expired_then_oldest() {
+        let disk_dir = std::env::temp_dir().join(format!(
+            "hey-cli-prompt-cache-sweep-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&disk_dir).unwrap();
+        let write = |name: &str, age_secs: u64| {
+            let path = disk_dir.join(name);
+            std::fs::write(&path, "{}").unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age_secs);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("expired.json", 120);
+        write("old.json", 30);
+        write("recent.json", 20);
+        write("new.json", 10);
+        write("notes.txt", 120);
+
+        sweep_disk(&disk_dir, 60, 2);
+        let mut names = std::fs::read_dir(&disk_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["new.json", "notes.txt", "recent.json"]);
+
+        std::fs::remove_dir_all(disk_dir).unwrap();
+    }
+
+    #[test]
+    fn configured_from_env() {
+        let cache = PromptCache::from_env(|key| match key {
+            "PROMPT_CACHE_CAPACITY" => Some("5".to_string()),
+            "PROMPT_CACHE_DIR" => Some("/tmp/hey".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(cache.capacity, 5);
+        assert_eq!(cache.ttl_secs, PromptCache::DEFAULT_TTL_SECS);
+        assert_eq!(cache.disk_dir, Some(PathBuf::from("/tmp/hey")));
+        assert_eq!(cache.disk_capacity, PromptCache::DEFAULT_DISK_CAPACITY);
+
+        let error = PromptCache::from_env(|key| {
+            (key == "PROMPT_CACHE_TTL_SECS").then(|| "a day".to_string())
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.to_string(), "Invalid PROMPT_CACHE_TTL_SECS: a day");
+    }
+}
@@ -0,0 +1,103 @@
+use super::{BoxFuture, PromptGenerator};
+use crate::error::ServerError;
+use hey_cli_common::{ChatMessage, ChatRole};
+use serde::{Deserialize, Serialize};
+
+/// Anthropic's messages API, or any server implementing it.
+pub struct Anthropic {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    system: String,
+    messages: Vec<Message>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+}
+
+impl Anthropic {
+    pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+    pub const DEFAULT_MODEL: &str = "claude-3-5-haiku-latest";
+    const API_VERSION: &str = "2023-06-01";
+
+    pub fn new(base_url: String, api_key: String, model: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        }
+    }
+}
+
+impl PromptGenerator for Anthropic {
+    fn generate<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+    ) -> BoxFuture<'a, Result<String, ServerError>> {
+        Box::pin(async move {
+            // system messages go in their own field, user ones are sent as a single turn
+            let content_of = |role: ChatRole| {
+                messages
+                    .iter()
+                    .filter(|message| message.role == role)
+                    .map(|message| message.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
+            let request = MessagesRequest {
+                model: &self.model,
+                max_tokens: 512,
+                system: content_of(ChatRole::System),
+                messages: vec![Message {
+                    role: "user",
+                    content: content_of(ChatRole::User),
+                }],
+            };
+
+            let response = self
+                .client
+                .post(format!("{}/v1/messages", self.base_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", Self::API_VERSION)
+                .json(&request)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<MessagesResponse>()
+                .await?;
+
+            response
+                .content
+                .into_iter()
+                .find(|block| block.kind == "text")
+                .and_then(|block| block.text)
+                .ok_or(ServerError::ModelUnavailable(
+                    "Model returned an empty response".to_string(),
+                ))
+        })
+    }
+}
@@ -0,0 +1,22 @@
+use super::{BoxFuture, PromptGenerator};
+use crate::error::ServerError;
+use hey_cli_common::{ChatMessage, ChatRole};
+
+/// Echoes back the last line of the last user message, which is the ask.
+pub struct Mock;
+
+impl PromptGenerator for Mock {
+    fn generate<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+    ) -> BoxFuture<'a, Result<String, ServerError>> {
+        let ask = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == ChatRole::User)
+            .and_then(|message| message.content.trim().lines().last())
+            .unwrap_or_default();
+
+        Box::pin(async move { Ok(format!("echo \"{ask}\"")) })
+    }
+}
@@ -0,0 +1,110 @@
+mod anthropic;
+mod mock;
+mod openai;
+
+pub use anthropic::Anthropic;
+pub use mock::Mock;
+pub use openai::OpenAi;
+
+use crate::error::ServerError;
+use anyhow::{Context, Result};
+use hey_cli_common::ChatMessage;
+use std::{future::Future, pin::Pin, sync::Arc};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Turns the chat messages built from the user's ask into the model's answer.
+pub trait PromptGenerator: Send + Sync {
+    fn generate<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+    ) -> BoxFuture<'a, Result<String, ServerError>>;
+}
+
+/// Picks the prompt generator from the `PROMPT_GENERATOR` env var:
+///
+/// - `openai` (default): `OPENAI_KEY`, optional `OPENAI_ORGANIZATION_ID` and `OPENAI_MODEL`
+/// - `openai_compatible`: `OPENAI_COMPATIBLE_BASE_URL`, optional `OPENAI_COMPATIBLE_API_KEY`
+///   and `OPENAI_COMPATIBLE_MODEL`, for Ollama, vLLM, LM Studio, ...
+/// - `anthropic`: `ANTHROPIC_API_KEY`, optional `ANTHROPIC_BASE_URL` and `ANTHROPIC_MODEL`
+/// - `mock`: answers deterministically without any network call
+pub fn from_env(env: impl Fn(&str) -> Option<String>) -> Result<Arc<dyn PromptGenerator>> {
+    let required = |key: &str| env(key).with_context(|| format!("Missing env var: {key}"));
+
+    let generator: Arc<dyn PromptGenerator> = match env("PROMPT_GENERATOR").as_deref() {
+        None | Some("openai") => Arc::new(OpenAi::new(
+            None,
+            Some(required("OPENAI_KEY")?),
+            env("OPENAI_ORGANIZATION_ID"),
+            env("OPENAI_MODEL").unwrap_or(OpenAi::DEFAULT_MODEL.to_string()),
+        )),
+        Some("openai_compatible") => Arc::new(OpenAi::new(
+            Some(required("OPENAI_COMPATIBLE_BASE_URL")?),
+            env("OPENAI_COMPATIBLE_API_KEY"),
+            None,
+            required("OPENAI_COMPATIBLE_MODEL")?,
+        )),
+        Some("anthropic") => Arc::new(Anthropic::new(
+            env("ANTHROPIC_BASE_URL").unwrap_or(Anthropic::DEFAULT_BASE_URL.to_string()),
+            required("ANTHROPIC_API_KEY")?,
+            env("ANTHROPIC_MODEL").unwrap_or(Anthropic::DEFAULT_MODEL.to_string()),
+        )),
+        Some("mock") => Arc::new(Mock),
+        Some(other) => anyhow::bail!("Unknown PROMPT_GENERATOR: {other}"),
+    };
+
+    Ok(generator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn defaults_to_openai_which_requires_a_key() {
+        let error = from_env(env(&[])).err().unwrap();
+        assert_eq!(error.to_string(), "Missing env var: OPENAI_KEY");
+        assert!(from_env(env(&[("OPENAI_KEY", "sk-test")])).is_ok());
+    }
+
+    #[test]
+    fn openai_compatible_requires_base_url_and_model() {
+        let vars = [("PROMPT_GENERATOR", "openai_compatible")];
+        assert!(from_env(env(&vars)).is_err());
+
+        let vars = [
+            ("PROMPT_GENERATOR", "openai_compatible"),
+            ("OPENAI_COMPATIBLE_BASE_URL", "http://localhost:11434/v1"),
+            ("OPENAI_COMPATIBLE_MODEL", "llama3.1"),
+        ];
+        assert!(from_env(env(&vars)).is_ok());
+    }
+
+    #[test]
+    fn unknown_generator_is_rejected() {
+        let error = from_env(env(&[("PROMPT_GENERATOR", "gpt-9")]))
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Unknown PROMPT_GENERATOR: gpt-9");
+    }
+
+    #[tokio::test]
+    async fn mock_answers_without_network() {
+        let generator = from_env(env(&[("PROMPT_GENERATOR", "mock")])).unwrap();
+        let messages = [
+            ChatMessage::system("some instructions"),
+            ChatMessage::user("user ask:\nprint working directory\n"),
+        ];
+        let value = generator.generate(&messages).await.unwrap();
+        assert_eq!(value, r#"echo "print working directory""#);
+    }
+}
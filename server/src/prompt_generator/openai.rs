@@ -0,0 +1,82 @@
+use super::{BoxFuture, PromptGenerator};
+use crate::error::ServerError;
+use async_openai::{
+    Client,
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs,
+    },
+};
+use hey_cli_common::{ChatMessage, ChatRole};
+
+/// OpenAI, or any server implementing its chat completions API when given a base URL.
+pub struct OpenAi {
+    client: Client<OpenAIConfig>,
+    model: String,
+}
+
+impl OpenAi {
+    pub const DEFAULT_MODEL: &str = "gpt-4o-2024-08-06";
+
+    pub fn new(
+        base_url: Option<String>,
+        api_key: Option<String>,
+        organization_id: Option<String>,
+        model: String,
+    ) -> Self {
+        let mut config = OpenAIConfig::new();
+        if let Some(base_url) = base_url {
+            config = config.with_api_base(base_url.trim_end_matches('/'));
+        }
+        if let Some(api_key) = api_key {
+            config = config.with_api_key(api_key);
+        }
+        if let Some(organization_id) = organization_id {
+            config = config.with_org_id(organization_id);
+        }
+
+        Self {
+            client: Client::with_config(config),
+            model,
+        }
+    }
+}
+
+impl PromptGenerator for OpenAi {
+    fn generate<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+    ) -> BoxFuture<'a, Result<String, ServerError>> {
+        Box::pin(async move {
+            let messages = messages
+                .iter()
+                .map(|message| match message.role {
+                    ChatRole::System => {
+                        ChatCompletionRequestSystemMessage::from(message.content.as_str()).into()
+                    }
+                    ChatRole::User => {
+                        ChatCompletionRequestUserMessage::from(message.content.as_str()).into()
+                    }
+                })
+                .collect::<Vec<ChatCompletionRequestMessage>>();
+
+            let request = CreateChatCompletionRequestArgs::default()
+                .max_tokens(512u32)
+                .model(&self.model)
+                .messages(messages)
+                .build()?;
+
+            let response = self.client.chat().create(request).await?;
+
+            response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message.content)
+                .ok_or(ServerError::ModelUnavailable(
+                    "Model returned an empty response".to_string(),
+                ))
+        })
+    }
+}
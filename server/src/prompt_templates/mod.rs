@@ -0,0 +1,506 @@
+use anyhow::{Context, Result};
+use hey_cli_common::{ChatMessage, ChatRole, GetCliPromptRequestBody};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+/// Variables templates can use as `{{name}}`, on top of `{{context.<section>}}`.
+const VARIABLES: &[&str] = &[
+    "ask",
+    "shell",
+    "os",
+    "context",
+    "instructions",
+    "response_format",
+];
+
+/// Without them the model wouldn't know what to answer, or how.
+const REQUIRED_VARIABLES: &[&str] = &["ask", "response_format"];
+
+/// The messages sent to the model for `/cli-prompt`, one `.toml` file each:
+///
+/// ```toml
+/// version = "concise-2"
+/// # picked twice as often as a template of weight 1, for A/B tests
+/// weight = 2
+///
+/// [[messages]]
+/// role = "system"
+/// content = "You write {{shell}} commands for {{os}}. {{response_format}}"
+///
+/// [[messages]]
+/// role = "user"
+/// content = "context: {{context}}\nask: {{ask}}"
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Template {
+    /// Returned along with the response, telling which template produced the command.
+    pub version: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    pub messages: Vec<ChatMessage>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl Template {
+    fn parse(content: &str) -> Result<Self> {
+        let template = toml::from_str::<Self>(content)?;
+        if template.version.trim().is_empty() {
+            anyhow::bail!("version is empty");
+        }
+
+        let variables = template
+            .messages
+            .iter()
+            .flat_map(|message| placeholders(&message.content))
+            .collect::<Vec<_>>();
+        if let Some(unknown) = variables
+            .iter()
+            .find(|variable| !VARIABLES.contains(variable) && !variable.starts_with("context."))
+        {
+            anyhow::bail!("unknown variable {{{{{unknown}}}}}");
+        }
+        if let Some(missing) = REQUIRED_VARIABLES
+            .iter()
+            .find(|required| !variables.contains(required))
+        {
+            anyhow::bail!("missing variable {{{{{missing}}}}}");
+        }
+
+        Ok(template)
+    }
+
+    /// Without `{{instructions}}`, the project's instructions are added before the first user
+    /// message as the built-in messages do, they're never left out.
+    pub fn render(&self, query: &GetCliPromptRequestBody) -> Vec<ChatMessage> {
+        let value = |variable: &str| match variable {
+            "ask" => query.ask.clone(),
+            "shell" => query.shell.clone().unwrap_or("unknown".to_string()),
+            "os" => {
+                let general = query.context.get("general");
+                let field = |key| general.and_then(|general| general.get(key)).cloned();
+                match (field("os_type"), field("os_version")) {
+                    (Some(os_type), Some(version)) => format!("{os_type} {version}"),
+                    (Some(os_type), None) => os_type,
+                    _ => "unknown".to_string(),
+                }
+            }
+            "context" => serde_json::to_string(&query.context).unwrap_or_default(),
+            "instructions" => query
+                .instructions
+                .iter()
+                .map(|instruction| format!("- {instruction}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "response_format" => query.response_format(),
+            _ => {
+                let section = variable
+                    .strip_prefix("context.")
+                    .and_then(|section| query.context.get(section));
+                section
+                    .and_then(|section| serde_json::to_string(section).ok())
+                    .unwrap_or("{}".to_string())
+            }
+        };
+
+        let mut messages = self
+            .messages
+            .iter()
+            .map(|message| ChatMessage {
+                role: message.role,
+                content: substitute(&message.content, value),
+            })
+            .collect::<Vec<_>>();
+
+        let uses_instructions = self
+            .messages
+            .iter()
+            .any(|message| placeholders(&message.content).any(|name| name == "instructions"));
+        if !uses_instructions && let Some(instructions) = query.instructions_message() {
+            let first_user_message = messages
+                .iter()
+                .position(|message| message.role == ChatRole::User)
+                .unwrap_or(messages.len());
+            messages.insert(first_user_message, instructions);
+        }
+        messages
+    }
+}
+
+/// The `name` of every `{{name}}`.
+fn placeholders(content: &str) -> impl Iterator<Item = &str> {
+    content.split("{{").skip(1).filter_map(|part| {
+        let (name, _) = part.split_once("}}")?;
+        Some(name.trim())
+    })
+}
+
+fn substitute(content: &str, value: impl Fn(&str) -> String) -> String {
+    let mut substituted = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        substituted.push_str(&rest[..start]);
+        substituted.push_str(&value(rest[start + 2..start + length].trim()));
+        rest = &rest[start + length + 2..];
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// Templates read from a directory, reloaded by [`Self::watch`] when its files change,
+/// off the request path since it reads the filesystem.
+pub struct PromptTemplates {
+    dir: Option<PathBuf>,
+    reload_interval: Duration,
+    loaded: RwLock<Loaded>,
+}
+
+#[derive(Default)]
+struct Loaded {
+    /// Sorted by file name, for the same ask to keep picking the same template.
+    templates: Vec<Arc<Template>>,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+impl PromptTemplates {
+    pub const DEFAULT_RELOAD_INTERVAL_SECS: u64 = 2;
+
+    /// Invalid templates fail here, but are only logged when reloading,
+    /// the previous ones being kept so a typo doesn't take the server down.
+    pub fn new(dir: Option<PathBuf>, reload_interval: Duration) -> Result<Self> {
+        let mut loaded = Loaded::default();
+        if let Some(dir) = &dir {
+            loaded.fingerprint = fingerprint(dir)?;
+            loaded.templates = load(&loaded.fingerprint)?;
+        }
+
+        Ok(Self {
+            dir,
+            reload_interval,
+            loaded: RwLock::new(loaded),
+        })
+    }
+
+    /// Configured by `PROMPT_TEMPLATES_DIR`, and `PROMPT_TEMPLATES_RELOAD_SECS` for how often
+    /// its files are checked for changes, 0 to never. Without templates the built-in messages
+    /// are used.
+    pub fn from_env(env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let reload_interval_secs = match env("PROMPT_TEMPLATES_RELOAD_SECS") {
+            Some(value) => value
+                .parse::<u64>()
+                .with_context(|| format!("Invalid PROMPT_TEMPLATES_RELOAD_SECS: {value}"))?,
+            None => Self::DEFAULT_RELOAD_INTERVAL_SECS,
+        };
+        Self::new(
+            env("PROMPT_TEMPLATES_DIR").map(PathBuf::from),
+            Duration::from_secs(reload_interval_secs),
+        )
+    }
+
+    /// `None` to use the built-in messages, otherwise a template picked by weight,
+    /// the same one for the same ask and context.
+    pub fn select(&self, query: &GetCliPromptRequestBody) -> Option<Arc<Template>> {
+        let loaded = self.loaded.read().unwrap();
+        let total_weight = loaded
+            .templates
+            .iter()
+            .map(|template| u64::from(template.weight))
+            .sum::<u64>();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut pick = u64::from_str_radix(&query.cache_key(), 16).unwrap_or(0) % total_weight;
+        for template in &loaded.templates {
+            let weight = u64::from(template.weight);
+            if pick < weight {
+                return Some(template.clone());
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    /// Checks for changes every `reload_interval` on a thread of its own, for as long as the
+    /// server runs.
+    pub fn watch(self: Arc<Self>) {
+        if self.dir.is_none() || self.reload_interval.is_zero() {
+            return;
+        }
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(self.reload_interval);
+                self.reload_if_changed();
+            }
+        });
+    }
+
+    /// Reads the files without holding the lock, requests keep being served meanwhile.
+    fn reload_if_changed(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let fingerprint = match fingerprint(dir) {
+            Ok(fingerprint) => fingerprint,
+            Err(error) => {
+                tracing::warn!("Could not read the prompt templates: {error:#}");
+                return;
+            }
+        };
+        if fingerprint == self.loaded.read().unwrap().fingerprint {
+            return;
+        }
+
+        let templates = load(&fingerprint);
+        let mut loaded = self.loaded.write().unwrap();
+        match templates {
+            Ok(templates) => {
+                tracing::info!("Reloaded {} prompt templates", templates.len());
+                loaded.templates = templates;
+            }
+            Err(error) => {
+                tracing::warn!("Kept the previous prompt templates: {error:#}");
+            }
+        }
+        // not retried until the files change again, a broken template would spam otherwise
+        loaded.fingerprint = fingerprint;
+    }
+}
+
+/// The template files, with what tells whether they changed.
+fn fingerprint(dir: &Path) -> Result<Vec<(PathBuf, Option<SystemTime>, u64)>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Invalid PROMPT_TEMPLATES_DIR: {}", dir.display()))?;
+    let mut fingerprint = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            Some((path, metadata.modified().ok(), metadata.len()))
+        })
+        .collect::<Vec<_>>();
+    fingerprint.sort();
+    Ok(fingerprint)
+}
+
+fn load(fingerprint: &[(PathBuf, Option<SystemTime>, u64)]) -> Result<Vec<Arc<Template>>> {
+    fingerprint
+        .iter()
+        .map(|(path, _, _)| {
+            let content = std::fs::read_to_string(path)?;
+            Template::parse(&content)
+                .map(Arc::new)
+                .with_context(|| format!("Invalid prompt template: {}", path.display()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hey_cli_common::ChatRole;
+    use std::collections::HashMap;
+
+    const TEMPLATE: &str = r#"
+version = "concise-1"
+
+[[messages]]
+role = "system"
+content = "You write {{ shell }} commands for {{os}}. {{response_format}}"
+
+[[messages]]
+role = "user"
+content = "git: {{context.git}}, k8s: {{context.containers}}\n{{instructions}}\nask: {{ask}}"
+"#;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "hey-cli-prompt-templates-test-{name}-{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, content: &str) {
+            std::fs::write(self.0.join(name), content).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn query(ask: &str) -> GetCliPromptRequestBody {
+        GetCliPromptRequestBody {
+            ask: ask.to_string(),
+            context: HashMap::from([
+                (
+                    "general".to_string(),
+                    HashMap::from([
+                        ("os_type".to_string(), "Debian".to_string()),
+                        ("os_version".to_string(), "12".to_string()),
+                    ]),
+                ),
+                (
+                    "git".to_string(),
+                    HashMap::from([("branch".to_string(), "main".to_string())]),
+                ),
+            ]),
+            candidates: 1,
+            explain: false,
+            instructions: vec!["use `just` for tasks".to_string()],
+            shell: Some("fish".to_string()),
+        }
+    }
+
+    #[test]
+    fn renders_variables() {
+        let template = Template::parse(TEMPLATE).unwrap();
+        let query = query("run the tests");
+        let messages = template.render(&query);
+
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert_eq!(
+            messages[0].content,
+            format!(
+                "You write fish commands for Debian 12. {}",
+                query.response_format()
+            )
+        );
+        assert_eq!(messages[1].role, ChatRole::User);
+        assert_eq!(
+            messages[1].content,
+            "git: {\"branch\":\"main\"}, k8s: {}\n- use `just` for tasks\nask: run the tests"
+        );
+    }
+
+    #[test]
+    fn appends_instructions_left_out_of_the_template() {
+        let template = Template::parse(&TEMPLATE.replace(r"\n{{instructions}}", "")).unwrap();
+        let query = query("run the tests");
+        let messages = template.render(&query);
+
+        assert_eq!(messages.len(), 3);
+        let instructions = query.instructions_message().unwrap();
+        assert_eq!(messages[1].role, instructions.role);
+        assert_eq!(messages[1].content, instructions.content);
+        assert_eq!(messages[2].role, ChatRole::User);
+        assert_eq!(
+            messages[2].content,
+            "git: {\"branch\":\"main\"}, k8s: {}\nask: run the tests"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let error = |content: &str| format!("{:#}", Template::parse(content).err().unwrap());
+
+        assert_eq!(
+            error(&TEMPLATE.replace("{{os}}", "{{distro}}")),
+            "unknown variable {{distro}}"
+        );
+        assert_eq!(
+            error(&TEMPLATE.replace("{{response_format}}", "")),
+            "missing variable {{response_format}}"
+        );
+        assert_eq!(
+            error(&TEMPLATE.replace("concise-1", " ")),
+            "version is empty"
+        );
+    }
+
+    #[test]
+    fn picks_by_weight_and_reloads() {
+        let dir = TempDir::new("reload");
+        dir.write("a.toml", TEMPLATE);
+        dir.write(
+            "b.toml",
+            &TEMPLATE.replace("\"concise-1\"", "\"verbose-1\"\nweight = 3"),
+        );
+        dir.write("notes.md", "not a template");
+
+        let templates = PromptTemplates::new(Some(dir.0.clone()), Duration::ZERO).unwrap();
+        let versions = (0..200)
+            .map(|index| {
+                let query = query(&format!("ask {index}"));
+                let version = templates.select(&query).unwrap().version.clone();
+                assert_eq!(templates.select(&query).unwrap().version, version);
+                version
+            })
+            .collect::<Vec<_>>();
+        let concise = versions
+            .iter()
+            .filter(|version| *version == "concise-1")
+            .count();
+        assert!((20..80).contains(&concise), "{concise}");
+
+        // a broken template keeps the previous ones
+        dir.write("b.toml", "version = ");
+        templates.reload_if_changed();
+        assert_eq!(
+            templates
+                .select(&query("ask 0"))
+                .map(|template| template.version.clone()),
+            Some(versions[0].clone())
+        );
+
+        dir.write("b.toml", &TEMPLATE.replace("concise-1", "concise-2"));
+        std::fs::remove_file(dir.0.join("a.toml")).unwrap();
+        templates.reload_if_changed();
+        assert_eq!(
+            templates.select(&query("ask 0")).unwrap().version,
+            "concise-2"
+        );
+
+        assert!(
+            PromptTemplates::new(None, Duration::ZERO)
+                .unwrap()
+                .select(&query("ask 0"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn configured_from_env() {
+        let error = PromptTemplates::from_env(|key| match key {
+            "PROMPT_TEMPLATES_DIR" => Some("/does/not/exist".to_string()),
+            _ => None,
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid PROMPT_TEMPLATES_DIR: /does/not/exist"
+        );
+
+        let error = PromptTemplates::from_env(|key| {
+            (key == "PROMPT_TEMPLATES_RELOAD_SECS").then(|| "often".to_string())
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid PROMPT_TEMPLATES_RELOAD_SECS: often"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+# exit when any command fails
+$ErrorActionPreference = "Stop"
+
+# map to rust target
+$target = "x86_64-pc-windows-msvc.exe"
+
+# vars
+$hey_cli_uri = "https://github.com/ZibanPirate/hey-cli/releases/latest/download/hey-$target"
+$hey_cli_install = if ($env:HEY_CLI_INSTALL) { $env:HEY_CLI_INSTALL } else { Join-Path $HOME ".hey_cli" }
+$bin_dir = Join-Path $hey_cli_install "bin"
+$exe = Join-Path $bin_dir "hey.exe"
+
+# ensure bin directory exists
+if (!(Test-Path $bin_dir)) {
+    New-Item $bin_dir -ItemType Directory | Out-Null
+}
+
+# download and move to bin
+try {
+    Invoke-WebRequest -Uri $hey_cli_uri -OutFile $exe -UseBasicParsing
+} catch {
+    Write-Output "Error: Failed to download hey-cli."
+    Write-Output "Please check your internet connection and ensure you can access:"
+    Write-Output $hey_cli_uri
+    Write-Output ""
+    Write-Output "If the problem persists, please report this issue at:"
+    Write-Output "https://github.com/ZibanPirate/hey-cli/issues"
+    exit 1
+}
+
+# add to PATH if not already present
+$user_path = [Environment]::GetEnvironmentVariable("Path", "User")
+if (!(";$user_path;".ToLower().Contains(";$bin_dir;".ToLower()))) {
+    [Environment]::SetEnvironmentVariable("Path", "$bin_dir;$user_path", "User")
+    $env:Path = "$bin_dir;$env:Path"
+    Write-Output "Added to PATH."
+}
+
+# print success message
+Write-Output "hey_cli was installed successfully to $exe"
+Write-Output "Please restart your terminal."
+Write-Output ""
+Write-Output "Stuck? contact me on: https://zak-man.com"